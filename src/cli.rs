@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use smart_road::sim::policy::SignalTimings;
+
+/// Command line options of the simulator.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub policy: Option<String>,
+    pub config: Option<String>,
+    pub scenario: Option<String>,
+    pub record: Option<String>,
+    pub stats: Option<String>,
+    pub halt_on_crash: bool,
+    pub replay: Option<String>,
+    pub signal_timings: SignalTimings,
+}
+
+pub const USAGE: &str = "Usage: smart-road [--config <file.toml>] [--scenario <name>] [--seed <u64>] [--policy <name>] [--signal <green>,<amber>,<red>] [--record <file>] [--stats <file>] [--halt-on-crash]
+       smart-road --replay <file>
+
+  --config    intersection layout and parameters (see config/default.toml)
+  --scenario  timed spawns to play back, a file or the name of one of scenarios/*.toml
+  --signal    phase durations in seconds of the traffic-light policy (default 6,2,3)
+  --record    save the session (restarted by 'c') to a file
+  --stats     on exit, write the statistics to <file>.json and <file>.csv
+  --halt-on-crash  stop the simulation at the first crash and print the vehicles' state
+  --replay    scrub through a recorded session: space play / pause, left / right one tick,
+              down / up one second, home / end first / last tick";
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or("--seed expects a value")?;
+                    let seed = value
+                        .parse::<u64>()
+                        .map_err(|e| format!("invalid --seed value '{}': {}", value, e))?;
+                    options.seed = Some(seed);
+                }
+                "--config" => {
+                    options.config = Some(args.next().ok_or("--config expects a file")?);
+                }
+                "--scenario" => {
+                    options.scenario = Some(args.next().ok_or("--scenario expects a value")?);
+                }
+                "--record" => {
+                    options.record = Some(args.next().ok_or("--record expects a file")?);
+                }
+                "--stats" => {
+                    options.stats = Some(args.next().ok_or("--stats expects a file")?);
+                }
+                "--halt-on-crash" => {
+                    options.halt_on_crash = true;
+                }
+                "--replay" => {
+                    options.replay = Some(args.next().ok_or("--replay expects a file")?);
+                }
+                "--policy" => {
+                    options.policy = Some(args.next().ok_or("--policy expects a value")?);
+                }
+                "--signal" => {
+                    let value = args.next().ok_or("--signal expects a value")?;
+                    options.signal_timings = parse_signal_timings(&value)?;
+                }
+                _ => {
+                    return Err(format!("unknown argument '{}'", arg));
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_signal_timings(value: &str) -> Result<SignalTimings, String> {
+    let durations = value
+        .split(',')
+        .map(|part| {
+            part.trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or(format!("invalid --signal duration '{}'", part))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match durations[..] {
+        [green, _, _] if green.is_zero() => {
+            Err(format!("--signal expects a positive green duration, got '{}'", value))
+        }
+        [green, amber, red] => Ok(SignalTimings { green, amber, red }),
+        _ => Err(format!("--signal expects <green>,<amber>,<red>, got '{}'", value)),
+    }
+}
@@ -0,0 +1,12 @@
+//! Smart Road simulation library.
+//!
+//! The `sim` module holds the whole traffic model and has no SDL dependency, so it can be
+//! driven headless (e.g. from tests or batch runs) by calling `VehiclesManagement::update`
+//! (`Network::update` for a grid of intersections) in a loop. The SDL2 drawing code lives in `render` and is only built with the `gui` feature.
+
+pub mod sim;
+#[cfg(feature = "gui")]
+pub mod render;
+
+pub const WINDOW_WIDTH: i32 = 1024;
+pub const WINDOW_HEIGHT: i32 = 768;
@@ -1,11 +1,22 @@
-mod sim;
-use sim::{
-    roads::{ Direction, Line, Road, RoadDirection },
+mod cli;
+mod replay;
+use cli::{ Options, USAGE };
+use smart_road::render::{ camera::{ Camera, PAN_SPEED }, vehicles::Sprites };
+use smart_road::sim::{
+    config::Config,
+    geometry::Geometry,
+    network::Network,
+    recording::Replay,
+    roads::{ Line, Road, RoadDirection },
+    scenario::Scenario,
+    stats::Stats,
+    trips::TimeSummary,
+    units::kmh,
     vehicles_management::VehiclesManagement,
 };
 use sdl2::{
     image::{ InitFlag, LoadTexture },
-    keyboard::Keycode,
+    keyboard::{ Keycode, Scancode },
     pixels::Color,
     rect::Rect,
     render::{ Texture, TextureQuery },
@@ -13,17 +24,31 @@ use sdl2::{
 };
 use std::{ path::Path, time::Duration };
 
-pub const WINDOW_WIDTH: i32 = 1024;
-pub const WINDOW_HEIGHT: i32 = 768;
 pub const TILE_SIZE: u32 = 64;
 
 fn main() -> Result<(), String> {
+    let options = Options::parse(std::env::args().skip(1)).map_err(|e|
+        format!("{}\n{}", e, USAGE)
+    )?;
+    let config = match &options.config {
+        Some(path) => Config::load(Path::new(path))?,
+        None => Config::default(),
+    };
+    let scenario = match &options.scenario {
+        Some(name) => Some(Scenario::by_name(name, &config)?),
+        None => None,
+    };
+    let replay = match &options.replay {
+        Some(path) => Some(Replay::load(Path::new(path))?),
+        None => None,
+    };
+    let geometry = replay.as_ref().map_or(config.geometry, |replay| replay.session.geometry);
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
     let _image_context = sdl2::image::init(InitFlag::PNG)?;
     let window = video_subsystem
-        .window("Smart Road", WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32)
+        .window("Smart Road", geometry.window_width as u32, geometry.window_height as u32)
         .position_centered()
         .build()
         .map_err(|e| e.to_string())?;
@@ -38,41 +63,40 @@ fn main() -> Result<(), String> {
     // Load texture
     let texture_creator = canvas.texture_creator();
     let tile_texture = load_texture(&texture_creator, "assets/sprites/space_bg.png")?;
-    let ship_north_texture = texture_creator.load_texture("assets/sprites/ship_north.png").unwrap();
-    let ship_west_texture = texture_creator.load_texture("assets/sprites/ship_west.png").unwrap();
-    let ship_south_texture = texture_creator.load_texture("assets/sprites/ship_south.png").unwrap();
-    let ship_est_texture = texture_creator.load_texture("assets/sprites/ship_est.png").unwrap();
+    let sprites = Sprites::load(&texture_creator, &config.vehicle_types)?;
+    if let Some(replay) = &replay {
+        return replay::run(replay, &mut canvas, &mut event_pump, &tile_texture, &sprites);
+    }
     // Pause game variable
     let mut paused = false;
     // Create roads
     let mut vertical_roads = Road::new();
     let mut horizontal_roads = Road::new();
-    let north_line = vec![
-        Line::new(RoadDirection::North, Direction::Left),
-        Line::new(RoadDirection::North, Direction::Straight),
-        Line::new(RoadDirection::North, Direction::Right)
-    ];
-    let west_line = vec![
-        Line::new(RoadDirection::West, Direction::Left),
-        Line::new(RoadDirection::West, Direction::Straight),
-        Line::new(RoadDirection::West, Direction::Right)
-    ];
-    let south_line = vec![
-        Line::new(RoadDirection::South, Direction::Left),
-        Line::new(RoadDirection::South, Direction::Straight),
-        Line::new(RoadDirection::South, Direction::Right)
-    ];
-    let est_line = vec![
-        Line::new(RoadDirection::East, Direction::Left),
-        Line::new(RoadDirection::East, Direction::Straight),
-        Line::new(RoadDirection::East, Direction::Right)
-    ];
+    let approach = |road_direction: RoadDirection| -> Vec<Line> {
+        config.approaches
+            .iter()
+            .find(|lines| lines[0].road_direction == road_direction)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let north_line = approach(RoadDirection::North);
+    let west_line = approach(RoadDirection::West);
+    let south_line = approach(RoadDirection::South);
+    let est_line = approach(RoadDirection::East);
     vertical_roads.lines1 = north_line.clone();
     vertical_roads.lines2 = south_line.clone();
     horizontal_roads.lines1 = west_line.clone();
     horizontal_roads.lines2 = est_line.clone();
     // Stock vehicles
-    let mut vehicles = VehiclesManagement::new();
+    let mut network = new_simulation(&options, options.seed, &config, scenario.as_ref())?;
+    let mut crash_reported = false;
+    let (world_width, world_height) = network.size();
+    let mut camera = Camera::new(
+        geometry.window_width,
+        geometry.window_height,
+        world_width,
+        world_height
+    );
 
     'running: loop {
         for event in event_pump.poll_iter() {
@@ -87,45 +111,102 @@ fn main() -> Result<(), String> {
                     paused = !paused;
                 }
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::Up), .. } =>
-                    vehicles.spawn(&south_line),
+                    network.spawn(RoadDirection::South),
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::Down), .. } =>
-                    vehicles.spawn(&north_line),
+                    network.spawn(RoadDirection::North),
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::Left), .. } =>
-                    vehicles.spawn(&west_line),
+                    network.spawn(RoadDirection::West),
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::Right), .. } =>
-                    vehicles.spawn(&est_line),
+                    network.spawn(RoadDirection::East),
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::R), .. } =>
-                    vehicles.spawn_random(vec![&north_line, &west_line, &south_line, &est_line]),
+                    network.spawn_random(),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::E), .. } =>
+                    network.spawn_emergency(),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::P), .. } =>
+                    network.spawn_pedestrian(),
                 sdl2::event::Event::KeyDown { keycode: Some(Keycode::C), .. } => {
-                    // Reset
-                    vehicles = VehiclesManagement::new();
+                    // Reset, keeping the seed so the run can be replayed
+                    network.finish_recording()?;
+                    network = new_simulation(
+                        &options,
+                        Some(network.seed),
+                        &config,
+                        scenario.as_ref()
+                    )?;
+                    crash_reported = false;
+                    canvas.window_mut().set_title("Smart Road").map_err(|e| e.to_string())?;
                 }
                 _ => {}
             }
         }
 
+        // Pan the camera over the grid of intersections
+        let keyboard = event_pump.keyboard_state();
+        let pan = |negative: Scancode, positive: Scancode| {
+            (keyboard.is_scancode_pressed(positive) as i32 -
+                keyboard.is_scancode_pressed(negative) as i32) * PAN_SPEED
+        };
+        camera.pan(pan(Scancode::A, Scancode::D), pan(Scancode::W, Scancode::S));
+
         if !paused {
+            canvas.set_viewport(None);
             canvas.set_draw_color(Color::RGB(0, 0, 0));
             canvas.clear();
             // Render background
-            draw_tiled_background(&mut canvas, &tile_texture);
+            draw_tiled_background(&mut canvas, &tile_texture, &geometry);
+            camera.apply(&mut canvas);
             // Renders roads
-            Road::render(&mut canvas);
+            for vehicles in &network.intersections {
+                Road::render(&mut canvas, &vehicles.geometry, &config.approaches, |line|
+                    vehicles.policy.signal(line)
+                );
+            }
             // Render & Update Vehicles
-            vehicles.update();
-            vehicles.render(
-                &mut canvas,
-                &[&ship_north_texture, &ship_west_texture, &ship_south_texture, &ship_est_texture]
-            );
+            network.update();
+            if let Some(vehicles) = network.halted().filter(|_| !crash_reported) {
+                eprintln!("Simulation halted. {}", vehicles.collisions.crashes[0].dump());
+                canvas
+                    .window_mut()
+                    .set_title("Smart Road - halted on a crash")
+                    .map_err(|e| e.to_string())?;
+                crash_reported = true;
+            }
+            for vehicles in &network.intersections {
+                vehicles.render(&mut canvas, &sprites);
+            }
 
             canvas.present();
         }
-        std::thread::sleep(Duration::from_millis(16));
+        std::thread::sleep(network.intersections[0].clock.dt);
+    }
+
+    network.finish_recording()?;
+    if let Some(path) = &options.stats {
+        // One export per intersection of a grid, suffixed with its row and column
+        for (index, vehicles) in network.intersections.iter().enumerate() {
+            let mut stats = Stats::new(vehicles);
+            stats.scenario = scenario.as_ref().map(|scenario| scenario.name.clone());
+            stats.config = options.config.clone();
+            let path = match network.position(index) {
+                _ if network.intersections.len() == 1 => path.clone(),
+                (row, column) => format!("{}_{}_{}", path, row, column),
+            };
+            stats.export(Path::new(&path))?;
+        }
+    }
+
+    let mut stats = if network.intersections.len() == 1 {
+        intersection_stats(&network.intersections[0])
+    } else {
+        network_stats(&network)
+    };
+    if let Some(scenario) = &scenario {
+        stats.insert(2, format!("Scenario: {}", scenario.name));
     }
 
     // Stats window
     let stats_window = video_subsystem
-        .window("Smart Road - Stats", 400, 300)
+        .window("Smart Road - Stats", 480, ((stats.len() as u32) * 26 + 20).max(450))
         .position_centered()
         .build()
         .map_err(|e| e.to_string())?;
@@ -143,25 +224,6 @@ fn main() -> Result<(), String> {
     // Load Font
     let font = ttf_context.load_font("assets/font/arial.ttf", 16)?;
 
-    let stats = vec![
-        format!("Number of vehicles spawned: {:?}", vehicles.number_of_vehicles),
-        format!(
-            "Number of vehicles that passed the intersection: {:?}",
-            vehicles.number_passed_intersection
-        ),
-        format!("Max velocity: {:?} pixel(s)", vehicles.max_velocity),
-        format!("Min velocity: {:?} pixel(s)", vehicles.min_velocity),
-        format!(
-            "Vehicle pass intersection Max time: {:?}s",
-            round_to_tenth_second(vehicles.max_time)
-        ),
-        format!(
-            "Vehicle pass intersection Min time: {:?}s",
-            round_to_tenth_second(vehicles.min_time)
-        ),
-        format!("Close calls: {:?}", vehicles.close_call)
-    ];
-
     'Stats_loop: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -176,7 +238,7 @@ fn main() -> Result<(), String> {
         }
 
         // Render background
-        draw_tiled_background(&mut stats_canvas, &tile_texture);
+        draw_tiled_background(&mut stats_canvas, &tile_texture, &geometry);
 
         // Render stats
         let mut y_offset = 10;
@@ -209,6 +271,135 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
+fn new_simulation(
+    options: &Options,
+    seed: Option<u64>,
+    config: &Config,
+    scenario: Option<&Scenario>
+) -> Result<Network, String> {
+    let seed = seed.unwrap_or_else(rand::random);
+    let mut network = Network::with_config(seed, config);
+    network.set_policies(options.policy.as_deref(), options.signal_timings)?;
+    if let Some(scenario) = scenario {
+        network.play(scenario);
+    }
+    for vehicles in &mut network.intersections {
+        vehicles.collisions.halt_on_crash = options.halt_on_crash;
+    }
+    if let Some(path) = &options.record {
+        network.record(Path::new(path))?;
+    }
+    Ok(network)
+}
+
+/// Lines of the stats window for a single intersection.
+fn intersection_stats(vehicles: &VehiclesManagement) -> Vec<String> {
+    let trip_report = vehicles.trip_report();
+    let safety = vehicles.safety.report();
+    let pedestrians = vehicles.pedestrians.report(&vehicles.clock);
+    vec![
+        format!("Seed: {}", vehicles.seed),
+        format!("Policy: {}", vehicles.policy.name()),
+        format!("Number of vehicles spawned: {:?}", vehicles.number_of_vehicles),
+        format!(
+            "Number of vehicles that passed the intersection: {:?}",
+            vehicles.number_passed_intersection
+        ),
+        format!("Max velocity: {:.1} km/h", kmh(vehicles.max_velocity)),
+        format!("Min velocity: {:.1} km/h", kmh(vehicles.min_velocity)),
+        format!(
+            "Vehicle pass intersection Max time: {:?}s",
+            round_to_tenth_second(vehicles.max_time)
+        ),
+        format!(
+            "Vehicle pass intersection Min time: {:?}s",
+            round_to_tenth_second(vehicles.min_time)
+        ),
+        format!(
+            "Crossing time mean / median / p95 / p99: {}",
+            format_summary(&trip_report.all.crossing_time)
+        ),
+        format!(
+            "Travel time mean / median / p95 / p99: {}",
+            format_summary(&trip_report.all.travel_time)
+        ),
+        format!("Crashes: {}", vehicles.collisions.crashes.len()),
+        format!(
+            "Close calls (TTC < {}s, PET < {}s, DRAC > {} m/s²): {}",
+            safety.thresholds.ttc,
+            safety.thresholds.pet,
+            safety.thresholds.drac,
+            safety.close_calls
+        ),
+        format!(
+            "Min TTC / min PET: {} / {}",
+            format_seconds(safety.min_ttc),
+            format_seconds(safety.min_pet)
+        ),
+        format!(
+            "Emergency delay mean / p95: {} / {}, imposed on others: {:.1}s",
+            format_seconds(trip_report.emergency.delay.mean),
+            format_seconds(trip_report.emergency.delay.p95),
+            trip_report.emergency.total_imposed_delay
+        ),
+        format!(
+            "Pedestrians crossed: {} / {}, wait mean / p95: {} / {}, conflicts: {}",
+            pedestrians.crossed,
+            pedestrians.spawned,
+            format_seconds(pedestrians.wait_time.mean),
+            format_seconds(pedestrians.wait_time.p95),
+            pedestrians.conflicts
+        )
+    ]
+}
+
+/// Lines of the stats window for a grid: the totals, then a summary per intersection.
+fn network_stats(network: &Network) -> Vec<String> {
+    let intersections = &network.intersections;
+    let mut stats = vec![
+        format!("Seed: {}", network.seed),
+        format!("Network: {} x {} intersections", network.layout.rows, network.layout.columns),
+        format!(
+            "Vehicles that entered the network: {}",
+            intersections
+                .iter()
+                .enumerate()
+                .flat_map(|(index, vehicles)| {
+                    vehicles.trips.iter().filter(move |trip| network.is_entry(index, trip.origin))
+                })
+                .count()
+        ),
+        format!(
+            "Reached their destination: {}, missed it: {}",
+            network.arrived,
+            network.missed
+        ),
+        format!(
+            "Crashes: {}",
+            intersections
+                .iter()
+                .map(|vehicles| vehicles.collisions.crashes.len())
+                .sum::<usize>()
+        )
+    ];
+    for (index, vehicles) in intersections.iter().enumerate() {
+        let (row, column) = network.position(index);
+        let crossing_time = vehicles.trip_report().all.crossing_time;
+        stats.push(
+            format!(
+                "({}, {}) {}: {} passed, crossing mean / p95: {} / {}",
+                row,
+                column,
+                vehicles.policy.name(),
+                vehicles.number_passed_intersection,
+                format_seconds(crossing_time.mean),
+                format_seconds(crossing_time.p95)
+            )
+        );
+    }
+    stats
+}
+
 fn load_texture<'a>(
     texture_creator: &'a sdl2::render::TextureCreator<WindowContext>,
     file_path: &str
@@ -218,11 +409,12 @@ fn load_texture<'a>(
 
 fn draw_tiled_background(
     canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    texture: &Texture
+    texture: &Texture,
+    geometry: &Geometry
 ) {
-    for y in (0..WINDOW_HEIGHT).step_by(TILE_SIZE as usize) {
-        for x in (0..WINDOW_WIDTH).step_by(TILE_SIZE as usize) {
-            let dest_rect = Rect::new(x as i32, y as i32, TILE_SIZE, TILE_SIZE);
+    for y in (0..geometry.window_height).step_by(TILE_SIZE as usize) {
+        for x in (0..geometry.window_width).step_by(TILE_SIZE as usize) {
+            let dest_rect = Rect::new(x, y, TILE_SIZE, TILE_SIZE);
             canvas.copy(texture, None, dest_rect).unwrap();
         }
     }
@@ -231,3 +423,18 @@ fn draw_tiled_background(
 pub fn round_to_tenth_second(duration: Duration) -> f64 {
     (duration.as_secs_f64() * 10.0).round() / 10.0
 }
+
+fn format_summary(summary: &TimeSummary) -> String {
+    [summary.mean, summary.median, summary.p95, summary.p99]
+        .into_iter()
+        .map(format_seconds)
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+fn format_seconds(seconds: Option<f64>) -> String {
+    match seconds {
+        Some(seconds) => format!("{:.1}s", seconds),
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,37 @@
+use sdl2::{ rect::Rect, render::Canvas, video::Window };
+
+/// Pixels the view moves by per frame while a pan key is held.
+pub const PAN_SPEED: i32 = 12;
+
+/// Part of the grid of intersections shown in the window, in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// Top-left corner of the view.
+    pub x: i32,
+    pub y: i32,
+    view_width: i32,
+    view_height: i32,
+    world_width: i32,
+    world_height: i32,
+}
+
+impl Camera {
+    /// View of `view_width` × `view_height` pixels at the top left of a world of
+    /// `world_width` × `world_height` pixels.
+    pub fn new(view_width: i32, view_height: i32, world_width: i32, world_height: i32) -> Self {
+        Camera { x: 0, y: 0, view_width, view_height, world_width, world_height }
+    }
+
+    /// Move the view by (`dx`, `dy`), keeping it inside the world.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        self.x = (self.x + dx).clamp(0, (self.world_width - self.view_width).max(0));
+        self.y = (self.y + dy).clamp(0, (self.world_height - self.view_height).max(0));
+    }
+
+    /// Draw the world from now on shifted by the position of the view.
+    pub fn apply(&self, canvas: &mut Canvas<Window>) {
+        canvas.set_viewport(
+            Rect::new(-self.x, -self.y, self.world_width as u32, self.world_height as u32)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! SDL2 drawing of the simulation state. Only built with the `gui` feature.
+
+pub mod camera;
+pub mod pedestrians;
+pub mod roads;
+pub mod vehicles;
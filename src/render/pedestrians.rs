@@ -0,0 +1,28 @@
+use sdl2::{ pixels::Color, rect::Rect, render::Canvas, video::Window };
+use crate::sim::{ geometry::Geometry, pedestrians::Pedestrian };
+
+/// Side of the square drawn for a pedestrian, in meters.
+const PEDESTRIAN_SIZE: f64 = 0.8;
+
+impl Pedestrian {
+    /// Draw the pedestrian as a square, brighter once it walks over the crosswalk.
+    pub fn render(&self, canvas: &mut Canvas<Window>, geometry: &Geometry) {
+        let size = geometry.to_pixels(PEDESTRIAN_SIZE).round().max(2.0);
+        let color = if self.is_crossing() {
+            Color::RGB(255, 220, 0)
+        } else {
+            Color::RGB(200, 150, 0)
+        };
+        canvas.set_draw_color(color);
+        canvas
+            .fill_rect(
+                Rect::new(
+                    (geometry.to_pixels(self.x) - size / 2.0).round() as i32,
+                    (geometry.to_pixels(self.y) - size / 2.0).round() as i32,
+                    size as u32,
+                    size as u32
+                )
+            )
+            .unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use sdl2::{ pixels::Color, rect::{ Point, Rect }, render::Canvas, video::Window };
+use crate::sim::{
+    geometry::Geometry,
+    policy::Signal,
+    roads::{ Direction, Line, Road, RoadDirection },
+};
+
+/// Length of the dashes of lane dividers, and of the gaps between them.
+const DASH_LENGTH: i32 = 20;
+/// Distance from the stop line to the manoeuvre arrows painted on each lane.
+const ARROW_DISTANCE: i32 = 70;
+/// Width of the stripes of the crosswalks, and of the gaps between them.
+const STRIPE_WIDTH: i32 = 6;
+
+impl Road {
+    /// Draw the roads, the lanes of `approaches` and the signal shown on each of them.
+    pub fn render(
+        canvas: &mut Canvas<Window>,
+        geometry: &Geometry,
+        approaches: &[Vec<Line>],
+        signal: impl Fn(&Line) -> Option<Signal>
+    ) {
+        let left = geometry.origin_x;
+        let top = geometry.origin_y;
+        let right = left + geometry.road_width();
+        let bottom = top + geometry.road_width();
+        // Edges of the area of the intersection
+        let (area_left, area_top) = (geometry.area_x, geometry.area_y);
+        let area_right = area_left + geometry.window_width;
+        let area_bottom = area_top + geometry.window_height;
+
+        // Render road border
+        canvas.set_draw_color(Color::RGB(250, 250, 250));
+        canvas.draw_line(Point::new(left, area_top), Point::new(left, top)).unwrap();
+        canvas.draw_line(Point::new(right, area_top), Point::new(right, top)).unwrap();
+
+        canvas.draw_line(Point::new(right, top), Point::new(area_right, top)).unwrap();
+        canvas.draw_line(Point::new(right, bottom), Point::new(area_right, bottom)).unwrap();
+
+        canvas.draw_line(Point::new(right, bottom), Point::new(right, area_bottom)).unwrap();
+        canvas.draw_line(Point::new(left, bottom), Point::new(left, area_bottom)).unwrap();
+
+        canvas.draw_line(Point::new(left, bottom), Point::new(area_left, bottom)).unwrap();
+        canvas.draw_line(Point::new(left, top), Point::new(area_left, top)).unwrap();
+
+        // Render lane dividers, the center line being solid
+        let lanes = geometry.lanes_per_approach;
+        for k in 1..2 * lanes {
+            let offset = k * geometry.lane_width;
+            let is_center = k == lanes;
+            if is_center {
+                canvas.set_draw_color(Color::RGB(230, 200, 0));
+            } else {
+                canvas.set_draw_color(Color::RGB(150, 150, 150));
+            }
+            let (x, y) = (left + offset, top + offset);
+            for (from, to) in [
+                (Point::new(x, area_top), Point::new(x, top)),
+                (Point::new(x, bottom), Point::new(x, area_bottom)),
+                (Point::new(area_left, y), Point::new(left, y)),
+                (Point::new(right, y), Point::new(area_right, y)),
+            ] {
+                Road::draw_divider(canvas, from, to, is_center);
+            }
+        }
+
+        // Render the crosswalks across the roads, at the edges of the intersection
+        canvas.set_draw_color(Color::RGB(200, 200, 200));
+        for road in [RoadDirection::North, RoadDirection::West, RoadDirection::South, RoadDirection::East] {
+            for stripe in Road::crosswalk_stripes(geometry, road) {
+                canvas.fill_rect(stripe).unwrap();
+            }
+        }
+
+        // Render the manoeuvres permitted on each lane
+        canvas.set_draw_color(Color::RGB(250, 250, 250));
+        for line in approaches.iter().flatten() {
+            for direction in &line.directions {
+                for stroke in Road::arrow(geometry, line, *direction) {
+                    canvas.draw_lines(&stroke[..]).unwrap();
+                }
+            }
+        }
+
+        // Render signal heads at the stop lines
+        for line in approaches.iter().flatten() {
+            if let Some(signal) = signal(line) {
+                let color = match signal {
+                    Signal::Green => Color::RGB(0, 200, 0),
+                    Signal::Amber => Color::RGB(255, 170, 0),
+                    Signal::Red => Color::RGB(220, 0, 0),
+                };
+                canvas.set_draw_color(color);
+                canvas.fill_rect(Road::signal_head(geometry, line)).unwrap();
+            }
+        }
+    }
+
+    /// Draw an horizontal or vertical lane divider from `from` to `to`.
+    fn draw_divider(canvas: &mut Canvas<Window>, from: Point, to: Point, solid: bool) {
+        if solid {
+            canvas.draw_line(from, to).unwrap();
+            return;
+        }
+        let length = (to.x() - from.x()).abs() + (to.y() - from.y()).abs();
+        let step = Point::new((to.x() - from.x()).signum(), (to.y() - from.y()).signum());
+        for start in (0..length).step_by((2 * DASH_LENGTH) as usize) {
+            let end = (start + DASH_LENGTH).min(length);
+            canvas.draw_line(from + step * start, from + step * end).unwrap();
+        }
+    }
+
+    /// Strokes of the arrow painted on `line` for `direction`, pointing to the stop line.
+    fn arrow(geometry: &Geometry, line: &Line, direction: Direction) -> Vec<Vec<Point>> {
+        // Arrow shapes in lane coordinates: u forward, v towards the right of the driver
+        let strokes: [[(i32, i32); 3]; 2] = match direction {
+            Direction::Straight => [[(0, 0), (15, 0), (30, 0)], [(24, -5), (30, 0), (24, 5)]],
+            Direction::Left => [[(0, 0), (18, 0), (18, -14)], [(13, -9), (18, -14), (23, -9)]],
+            Direction::Right => [[(0, 0), (18, 0), (18, 14)], [(13, 9), (18, 14), (23, 9)]],
+        };
+
+        let lane_center = geometry.lane_center(line.road_direction, line.lane);
+        let left = geometry.origin_x;
+        let top = geometry.origin_y;
+        let right = left + geometry.road_width();
+        let bottom = top + geometry.road_width();
+        let (stop, forward, driver_right) = match line.road_direction {
+            RoadDirection::North => (Point::new(lane_center, top), Point::new(0, 1), Point::new(-1, 0)),
+            RoadDirection::South => (Point::new(lane_center, bottom), Point::new(0, -1), Point::new(1, 0)),
+            RoadDirection::West => (Point::new(right, lane_center), Point::new(-1, 0), Point::new(0, -1)),
+            RoadDirection::East => (Point::new(left, lane_center), Point::new(1, 0), Point::new(0, 1)),
+        };
+        let anchor = stop - forward * ARROW_DISTANCE;
+
+        strokes
+            .iter()
+            .map(|stroke| {
+                stroke
+                    .iter()
+                    .map(|&(u, v)| anchor + forward * u + driver_right * v)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Stripes of the crosswalk over the road of `road`, parallel to its lanes.
+    fn crosswalk_stripes(geometry: &Geometry, road: RoadDirection) -> Vec<Rect> {
+        let crosswalk = geometry.crosswalk(road);
+        let x = geometry.to_pixels(crosswalk.x).round() as i32;
+        let y = geometry.to_pixels(crosswalk.y).round() as i32;
+        let width = geometry.to_pixels(crosswalk.width).round() as i32;
+        let height = geometry.to_pixels(crosswalk.height).round() as i32;
+        let stripe = STRIPE_WIDTH as u32;
+        match road {
+            RoadDirection::North | RoadDirection::South =>
+                (STRIPE_WIDTH / 2..width - STRIPE_WIDTH)
+                    .step_by((2 * STRIPE_WIDTH) as usize)
+                    .map(|offset| Rect::new(x + offset, y, stripe, height as u32))
+                    .collect(),
+            RoadDirection::West | RoadDirection::East =>
+                (STRIPE_WIDTH / 2..height - STRIPE_WIDTH)
+                    .step_by((2 * STRIPE_WIDTH) as usize)
+                    .map(|offset| Rect::new(x, y + offset, width as u32, stripe))
+                    .collect(),
+        }
+    }
+
+    /// Rectangle of the signal head drawn in front of the stop line of `line`, before the
+    /// crosswalk.
+    fn signal_head(geometry: &Geometry, line: &Line) -> Rect {
+        let lane = geometry.lane_center(line.road_direction, line.lane);
+        let offset = lane - geometry.lane_width / 2 + 5;
+        let length = (geometry.lane_width as u32).saturating_sub(10);
+        let stop_area = geometry.stop_area();
+        let left = geometry.to_pixels(stop_area.x).round() as i32;
+        let top = geometry.to_pixels(stop_area.y).round() as i32;
+        let right = geometry.to_pixels(stop_area.x + stop_area.width).round() as i32;
+        let bottom = geometry.to_pixels(stop_area.y + stop_area.height).round() as i32;
+        match line.road_direction {
+            RoadDirection::North => Rect::new(offset, top - 12, length, 8),
+            RoadDirection::West => Rect::new(right + 4, offset, 8, length),
+            RoadDirection::South => Rect::new(offset, bottom + 4, length, 8),
+            RoadDirection::East => Rect::new(left - 12, offset, 8, length),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use sdl2::{
+    image::LoadTexture,
+    pixels::Color,
+    rect::{ Point, Rect },
+    render::{ Canvas, Texture, TextureCreator },
+    video::{ Window, WindowContext },
+};
+use crate::sim::{
+    geometry::Geometry,
+    roads::RoadDirection,
+    vehicle::Vehicle,
+    vehicle_type::{ VehicleCatalog, VehicleKind },
+    vehicles_management::VehiclesManagement,
+};
+
+/// Sprites of every vehicle type of a catalog, one per road direction.
+pub struct Sprites<'a> {
+    /// North, West, South and East sprites, by sprite name.
+    textures: HashMap<String, [Texture<'a>; 4]>,
+    names: HashMap<VehicleKind, String>,
+}
+
+impl<'a> Sprites<'a> {
+    pub fn load(
+        texture_creator: &'a TextureCreator<WindowContext>,
+        catalog: &VehicleCatalog
+    ) -> Result<Self, String> {
+        let mut sprites = Sprites { textures: HashMap::new(), names: HashMap::new() };
+        for vehicle_type in catalog.iter() {
+            let name = &vehicle_type.sprite;
+            if !sprites.textures.contains_key(name) {
+                let [north, west, south, east] = ["north", "west", "south", "est"].map(|suffix| {
+                    let path = format!("assets/sprites/{}_{}.png", name, suffix);
+                    texture_creator
+                        .load_texture(&path)
+                        .map_err(|e| format!("cannot load sprite '{}': {}", path, e))
+                });
+                sprites.textures.insert(name.clone(), [north?, west?, south?, east?]);
+            }
+            sprites.names.insert(vehicle_type.kind, name.clone());
+        }
+        Ok(sprites)
+    }
+
+    /// Sprite of the vehicles of `kind` driving on `road_direction`, `None` for a kind
+    /// missing from the catalog the sprites were loaded from.
+    fn get(&self, kind: VehicleKind, road_direction: RoadDirection) -> Option<&Texture<'a>> {
+        let textures = &self.textures[self.names.get(&kind)?];
+        Some(match road_direction {
+            RoadDirection::North => &textures[0],
+            RoadDirection::West => &textures[1],
+            RoadDirection::South => &textures[2],
+            RoadDirection::East => &textures[3],
+        })
+    }
+}
+
+impl VehiclesManagement {
+    pub fn render(&self, canvas: &mut Canvas<Window>, sprites: &Sprites) {
+        for vehicle in &self.list {
+            if self.geometry.is_on_screen(vehicle) {
+                vehicle.render(canvas, sprites, &self.geometry);
+            }
+        }
+        for pedestrian in &self.pedestrians.list {
+            pedestrian.render(canvas, &self.geometry);
+        }
+
+        // Highlight the crashes: the vehicles still overlapping and a cross where each happened
+        for vehicle in &self.list {
+            if self.collisions.is_colliding(vehicle.id) {
+                vehicle.render_crash(canvas, &self.geometry);
+            }
+        }
+        canvas.set_draw_color(Color::RGB(255, 0, 0));
+        for crash in &self.collisions.crashes {
+            let [first, second] = &crash.vehicles;
+            let center = Point::new(
+                self.geometry.to_pixels((first.x + second.x) / 2.0).round() as i32,
+                self.geometry.to_pixels((first.y + second.y) / 2.0).round() as i32
+            );
+            for (dx, dy) in [(1, 1), (1, -1)] {
+                canvas
+                    .draw_line(center - Point::new(dx, dy) * 8, center + Point::new(dx, dy) * 8)
+                    .unwrap();
+            }
+        }
+    }
+}
+
+impl Vehicle {
+    /// Draw the vehicle with the sprite of its kind and road, stretched over its body and
+    /// rotated to its heading while it turns, at the scale of `geometry`.
+    pub fn render(&self, canvas: &mut Canvas<Window>, sprites: &Sprites, geometry: &Geometry) {
+        let Some(sprite) = sprites.get(self.kind, self.road_direction) else {
+            return;
+        };
+        let (length, width) = (geometry.to_pixels(self.length), geometry.to_pixels(self.width));
+        let (rect_width, rect_height) = match self.road_direction {
+            RoadDirection::North | RoadDirection::South => (width, length),
+            RoadDirection::West | RoadDirection::East => (length, width),
+        };
+        let dest_rect = Rect::new(
+            (geometry.to_pixels(self.x) - rect_width / 2.0).round() as i32,
+            (geometry.to_pixels(self.y) - rect_height / 2.0).round() as i32,
+            rect_width.round() as u32,
+            rect_height.round() as u32
+        );
+        let angle = self.heading - self.road_direction.heading();
+        canvas.copy_ex(sprite, None, dest_rect, angle, None, false, false).unwrap();
+        if self.kind == VehicleKind::Emergency {
+            self.render_beacon(canvas, geometry);
+        }
+    }
+
+    /// Red and blue light bar across the middle of an emergency vehicle.
+    fn render_beacon(&self, canvas: &mut Canvas<Window>, geometry: &Geometry) {
+        let (cx, cy) = (geometry.to_pixels(self.x), geometry.to_pixels(self.y));
+        let half = (geometry.to_pixels(self.width) / 4.0).round() as i32;
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+        // Across the heading, red on the left half and blue on the right one
+        for (side, color) in [(-1.0, Color::RGB(255, 0, 0)), (1.0, Color::RGB(0, 0, 255))] {
+            let x = (cx - side * sin * (half as f64)).round() as i32;
+            let y = (cy + side * cos * (half as f64)).round() as i32;
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new(x - half / 2, y - half / 2, half as u32, half as u32)).unwrap();
+        }
+    }
+
+    /// Outline the footprint of a vehicle involved in a crash.
+    pub fn render_crash(&self, canvas: &mut Canvas<Window>, geometry: &Geometry) {
+        canvas.set_draw_color(Color::RGB(255, 0, 0));
+        let corners = self.corners();
+        let (cx, cy) = (geometry.to_pixels(self.x), geometry.to_pixels(self.y));
+        for inset in 0..3 {
+            // Shrink the outline towards the center by `inset` pixels
+            let scale = 1.0 - (inset as f64) / (geometry.to_pixels(self.length) / 2.0);
+            let mut points: Vec<Point> = corners
+                .iter()
+                .map(|&(x, y)| {
+                    let (x, y) = (geometry.to_pixels(x), geometry.to_pixels(y));
+                    Point::new(
+                        (cx + (x - cx) * scale).round() as i32,
+                        (cy + (y - cy) * scale).round() as i32
+                    )
+                })
+                .collect();
+            points.push(points[0]);
+            canvas.draw_lines(&points[..]).unwrap();
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use sdl2::{
+    event::Event,
+    keyboard::Keycode,
+    pixels::Color,
+    render::{ Canvas, Texture },
+    video::Window,
+    EventPump,
+};
+use smart_road::{ render::vehicles::Sprites, sim::{ recording::Replay, roads::Road } };
+
+use crate::draw_tiled_background;
+
+/// Scrub through a recorded session with the live renderer:
+/// space plays / pauses, left / right step one tick back / forward, down / up jump one
+/// second back / forward, home / end go to the first / last tick and escape quits.
+pub fn run(
+    replay: &Replay,
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    background: &Texture,
+    sprites: &Sprites
+) -> Result<(), String> {
+    if replay.frames.is_empty() {
+        return Err("the recording has no frame to replay".to_string());
+    }
+    let session = &replay.session;
+    let last = replay.frames.len() - 1;
+    let ticks_per_second = (1.0 / session.dt.as_secs_f64()).round().max(1.0) as usize;
+    let mut index = 0;
+    let mut playing = true;
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    break 'running;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
+                    playing = !playing;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                    playing = false;
+                    index = (index + 1).min(last);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                    playing = false;
+                    index = index.saturating_sub(1);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                    index = (index + ticks_per_second).min(last);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
+                    index = index.saturating_sub(ticks_per_second);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Home), .. } => {
+                    index = 0;
+                }
+                Event::KeyDown { keycode: Some(Keycode::End), .. } => {
+                    index = last;
+                }
+                _ => {}
+            }
+        }
+
+        let frame = &replay.frames[index];
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        draw_tiled_background(canvas, background, &session.geometry);
+        Road::render(canvas, &session.geometry, &session.approaches, |line|
+            replay.signal(frame, line)
+        );
+        for vehicle in &frame.vehicles {
+            vehicle.render(canvas, sprites, &session.geometry);
+        }
+        for pedestrian in &frame.pedestrians {
+            pedestrian.render(canvas, &session.geometry);
+        }
+        for vehicle in &frame.vehicles {
+            if frame.vehicles.iter().any(|other| other.id != vehicle.id && vehicle.overlaps(other)) {
+                vehicle.render_crash(canvas, &session.geometry);
+            }
+        }
+        canvas
+            .window_mut()
+            .set_title(
+                &format!(
+                    "Smart Road - Replay {:.1}s (tick {}) - {}{}",
+                    session.dt.as_secs_f64() * (frame.tick as f64),
+                    frame.tick,
+                    session.policy,
+                    if playing { "" } else { " - paused" }
+                )
+            )
+            .map_err(|e| e.to_string())?;
+        canvas.present();
+
+        if playing {
+            if index < last {
+                index += 1;
+            } else {
+                playing = false;
+            }
+        }
+        std::thread::sleep(session.dt);
+    }
+
+    Ok(())
+}
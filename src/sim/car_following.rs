@@ -0,0 +1,124 @@
+use std::time::Duration;
+use serde::{ Deserialize, Serialize };
+
+use super::vehicle::Vehicle;
+
+/// Acceleration exponent of the Intelligent Driver Model.
+const IDM_EXPONENT: i32 = 4;
+
+/// Model deciding the velocity of a vehicle on an approach lane from the vehicle ahead of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CarFollowing {
+    /// The original rules: fast on a clear road, slow close behind another vehicle.
+    #[default]
+    Rules,
+    /// Intelligent Driver Model, with the `fast` velocity of the `DrivingProfile` as desired
+    /// velocity, its safe distance as minimum gap, its maximum acceleration and comfortable
+    /// deceleration, and its time headway.
+    Idm,
+}
+
+/// Velocity `vehicle` aims for after a tick of `dt` according to the Intelligent Driver
+/// Model, given the gap to the vehicle ahead and that vehicle, if any.
+pub fn idm_velocity(vehicle: &Vehicle, leader: Option<(f64, &Vehicle)>, dt: Duration) -> f64 {
+    let profile = &vehicle.profile;
+    let velocity = vehicle.velocity;
+    let free_road = 1.0 - (velocity / profile.fast).powi(IDM_EXPONENT);
+
+    let interaction = match leader {
+        None => 0.0,
+        Some((gap, _)) if gap <= 0.0 => {
+            return 0.0;
+        }
+        Some((gap, leader)) => {
+            // Closing velocity along the heading of the follower
+            let (sin, cos) = vehicle.heading.to_radians().sin_cos();
+            let (leader_x, leader_y) = leader.velocity_vector();
+            let approaching = velocity - (leader_x * cos + leader_y * sin);
+            let braking =
+                (velocity * approaching) /
+                (2.0 * (profile.max_acceleration * profile.comfortable_deceleration).sqrt());
+            let desired_gap =
+                profile.safe_distance + (velocity * profile.time_headway + braking).max(0.0);
+            (desired_gap / gap).powi(2)
+        }
+    };
+    velocity + profile.max_acceleration * (free_road - interaction) * dt.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        clock::Clock,
+        config::Config,
+        roads::{ Direction, RoadDirection },
+        testing::car,
+    };
+
+    /// Car following the Intelligent Driver Model from the start of the East approach at
+    /// `velocity`, and the clock it drives with.
+    fn idm_car(id: i32, velocity: f64) -> (Vehicle, Clock) {
+        let config = Config::default();
+        let mut vehicle = car(id, &config, RoadDirection::East, Direction::Straight);
+        vehicle.profile.car_following = CarFollowing::Idm;
+        vehicle.velocity = velocity;
+        (vehicle, Clock::new(config.tick_duration))
+    }
+
+    /// Drive `follower` for `seconds` behind `leader`, which keeps its velocity, checking that
+    /// they never overlap.
+    fn follow(follower: &mut Vehicle, leader: &mut Vehicle, clock: &Clock, seconds: f64) {
+        for _ in 0..(seconds / clock.dt.as_secs_f64()) as u64 {
+            let ahead = follower.leader(std::slice::from_ref(leader));
+            let velocity = idm_velocity(follower, ahead, clock.dt);
+            follower.accelerate(velocity, clock.dt);
+            follower.step(clock.dt);
+            leader.step(clock.dt);
+            assert!(!follower.overlaps(leader));
+        }
+    }
+
+    #[test]
+    fn speeds_up_to_the_desired_velocity_on_a_free_road() {
+        let (mut vehicle, clock) = idm_car(0, 0.0);
+        let dt = clock.dt.as_secs_f64();
+        let velocity = idm_velocity(&vehicle, None, clock.dt);
+        assert!((velocity - vehicle.profile.max_acceleration * dt).abs() < 1e-9);
+        for _ in 0..(60.0 / dt) as u64 {
+            let velocity = idm_velocity(&vehicle, None, clock.dt);
+            vehicle.accelerate(velocity, clock.dt);
+            assert!(vehicle.velocity <= vehicle.profile.fast);
+        }
+        assert!(vehicle.velocity > 0.99 * vehicle.profile.fast, "{} m/s", vehicle.velocity);
+    }
+
+    #[test]
+    fn keeps_the_equilibrium_gap_behind_a_steady_leader() {
+        let (mut leader, clock) = idm_car(0, 8.0);
+        leader.x += 30.0;
+        let (mut follower, _) = idm_car(1, 8.0);
+        follow(&mut follower, &mut leader, &clock, 120.0);
+
+        let profile = follower.profile;
+        let expected =
+            (profile.safe_distance + 8.0 * profile.time_headway) /
+            (1.0 - (8.0 / profile.fast).powi(IDM_EXPONENT)).sqrt();
+        let gap = follower.gap_to(&leader).unwrap();
+        assert!((gap - expected).abs() < 0.02 * expected, "{} m instead of {} m", gap, expected);
+        assert!((follower.velocity - 8.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn stops_behind_a_standing_vehicle() {
+        let (mut leader, clock) = idm_car(0, 0.0);
+        leader.x += 60.0;
+        let (mut follower, _) = idm_car(1, 0.0);
+        follower.velocity = follower.profile.fast;
+        follow(&mut follower, &mut leader, &clock, 60.0);
+        assert!(follower.velocity < 0.01, "{} m/s", follower.velocity);
+        let gap = follower.gap_to(&leader).unwrap();
+        assert!(gap > 0.5 * follower.profile.safe_distance, "stopped {} m behind", gap);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+/// Simulated time elapsed per call to `VehiclesManagement::update` (one frame at ~60 fps).
+pub const TICK_DURATION: Duration = Duration::from_millis(16);
+
+/// Simulation clock: a tick counter advanced once per update with a fixed `dt`.
+/// Everything in `sim` measures time with it so results don't depend on the host speed.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    pub tick: u64,
+    pub dt: Duration,
+}
+
+impl Clock {
+    pub fn new(dt: Duration) -> Self {
+        Clock { tick: 0, dt }
+    }
+
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Simulated time since `tick`.
+    pub fn elapsed(&self, tick: u64) -> Duration {
+        self.duration(self.tick.saturating_sub(tick))
+    }
+
+    /// Simulated time covered by `ticks` ticks.
+    pub fn duration(&self, ticks: u64) -> Duration {
+        Duration::from_nanos((self.dt.as_nanos() as u64) * ticks)
+    }
+
+    /// Simulated time since the start of the run.
+    pub fn now(&self) -> Duration {
+        self.duration(self.tick)
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(TICK_DURATION)
+    }
+}
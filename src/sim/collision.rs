@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+use serde::Serialize;
+
+use super::{ clock::Clock, vehicle::Vehicle };
+
+/// Two vehicles whose footprints overlapped, with their state at the first tick of overlap.
+#[derive(Debug, Clone, Serialize)]
+pub struct Crash {
+    pub tick: u64,
+    /// Simulated time of the crash, in seconds.
+    pub time: f64,
+    pub vehicles: [Vehicle; 2],
+}
+
+impl Crash {
+    /// Human readable description of the crash and of the state of both vehicles.
+    pub fn dump(&self) -> String {
+        let [first, second] = &self.vehicles;
+        format!(
+            "Crash at tick {} ({:.3}s) between vehicles {} and {}:\n{}\n{}",
+            self.tick,
+            self.time,
+            first.id,
+            second.id,
+            serde_json::to_string_pretty(first).expect("vehicles are always serializable"),
+            serde_json::to_string_pretty(second).expect("vehicles are always serializable")
+        )
+    }
+}
+
+/// Checks every tick whether the footprints of two vehicles overlap, independently of the
+/// policy that is supposed to prevent it. A pair of vehicles counts as one crash however
+/// long the overlap lasts.
+#[derive(Debug, Default)]
+pub struct CollisionDetector {
+    pub crashes: Vec<Crash>,
+    /// Stop the simulation at the first crash.
+    pub halt_on_crash: bool,
+    /// Ids of the vehicles overlapping another one at the last check.
+    colliding: HashSet<i32>,
+    crashed_pairs: HashSet<(i32, i32)>,
+}
+
+impl CollisionDetector {
+    pub fn detect(&mut self, vehicles: &[Vehicle], clock: &Clock) {
+        self.colliding.clear();
+        for (i, a) in vehicles.iter().enumerate() {
+            for b in &vehicles[i + 1..] {
+                if !a.overlaps(b) {
+                    continue;
+                }
+                self.colliding.insert(a.id);
+                self.colliding.insert(b.id);
+                if self.crashed_pairs.insert((a.id.min(b.id), a.id.max(b.id))) {
+                    self.crashes.push(Crash {
+                        tick: clock.tick,
+                        time: clock.now().as_secs_f64(),
+                        vehicles: [a.clone(), b.clone()],
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn is_colliding(&self, vehicle_id: i32) -> bool {
+        self.colliding.contains(&vehicle_id)
+    }
+
+    /// Whether the simulation stopped on a crash.
+    pub fn is_halted(&self) -> bool {
+        self.halt_on_crash && !self.crashes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        config::Config,
+        policy::ReactivePolicy,
+        roads::{ Direction, RoadDirection },
+        vehicle_type::VehicleKind,
+        vehicles_management::VehiclesManagement,
+    };
+
+    /// Car centered on (`x`, `y`) heading `heading` degrees.
+    fn car(id: i32, x: f64, y: f64, heading: f64) -> Vehicle {
+        let config = Config::default();
+        let car = config.vehicle_types.get(VehicleKind::Car);
+        let mut vehicle = Vehicle::new(id, RoadDirection::East, Direction::Straight, x, y, car);
+        vehicle.heading = heading;
+        vehicle
+    }
+
+    #[test]
+    fn bodies_overlap_with_their_heading() {
+        // 5 m by 4 m cars
+        assert!(car(0, 0.0, 0.0, 0.0).overlaps(&car(1, 4.9, 0.0, 0.0)));
+        assert!(!car(0, 0.0, 0.0, 0.0).overlaps(&car(1, 5.0, 0.0, 0.0)));
+        assert!(car(0, 0.0, 0.0, 0.0).overlaps(&car(1, 0.0, 4.0, 90.0)));
+        // Their bounding boxes overlap, but not the bodies turned by 45°
+        let (sin, cos) = 45f64.to_radians().sin_cos();
+        let side = car(1, -4.1 * sin, 4.1 * cos, 45.0);
+        assert!(!car(0, 0.0, 0.0, 45.0).overlaps(&side));
+    }
+
+    #[test]
+    fn each_pair_counts_once_while_it_overlaps() {
+        let mut detector = CollisionDetector::default();
+        let mut clock = Clock::default();
+        let mut vehicles = vec![
+            car(0, 0.0, 0.0, 0.0),
+            car(1, 10.0, 0.0, 180.0),
+            car(2, 30.0, 0.0, 0.0),
+        ];
+        for tick in 0..10 {
+            clock.tick = tick;
+            // The first two close in on each other by a meter per tick
+            vehicles[0].x = tick as f64 * 0.5;
+            vehicles[1].x = 10.0 - tick as f64 * 0.5;
+            detector.detect(&vehicles, &clock);
+        }
+        assert_eq!(detector.crashes.len(), 1);
+        let crash = &detector.crashes[0];
+        // Bodies 5 m long overlap once their centers are closer than 5 m
+        assert_eq!(crash.tick, 6);
+        assert_eq!(crash.vehicles.each_ref().map(|vehicle| vehicle.id), [0, 1]);
+        assert!(detector.is_colliding(0) && detector.is_colliding(1));
+        assert!(!detector.is_colliding(2));
+        assert!(!detector.is_halted());
+    }
+
+    #[test]
+    fn simulation_halts_on_the_first_crash() {
+        let config = Config::default();
+        let mut vehicles = VehiclesManagement::with_config(0, &config);
+        vehicles.set_policy(Box::new(ReactivePolicy));
+        vehicles.collisions.halt_on_crash = true;
+        // Nothing keeps the vehicles of different approaches apart under the reactive policy
+        while !vehicles.collisions.is_halted() {
+            assert!(vehicles.clock.tick < 10_000, "no crash");
+            vehicles.spawn_random(config.approaches.iter().collect());
+            vehicles.update();
+        }
+        let tick = vehicles.clock.tick;
+        let positions = |vehicles: &VehiclesManagement| {
+            vehicles.list
+                .iter()
+                .map(|vehicle| (vehicle.x, vehicle.y))
+                .collect::<Vec<_>>()
+        };
+        let before = positions(&vehicles);
+        vehicles.update();
+        assert_eq!(vehicles.clock.tick, tick);
+        assert_eq!(vehicles.collisions.crashes.len(), 1);
+        let [first, second] = &vehicles.collisions.crashes[0].vehicles;
+        assert!(first.overlaps(second));
+        assert_eq!(positions(&vehicles), before);
+    }
+}
@@ -0,0 +1,704 @@
+use std::{ collections::BTreeMap, fs, path::Path, time::Duration };
+use serde::Deserialize;
+
+use super::{
+    car_following::CarFollowing,
+    clock::TICK_DURATION,
+    geometry::Geometry,
+    network::NetworkLayout,
+    pedestrians::PedestrianTraffic,
+    policy::POLICY_NAMES,
+    safety::SafetyThresholds,
+    roads::{ Direction, Line, RoadDirection },
+    units::{ kmh, meters_per_second, DEFAULT_PIXELS_PER_METER },
+    vehicle::DrivingProfile,
+    vehicle_type::{ TrafficMix, VehicleCatalog, VehicleKind },
+    vehicles_management::SPAWN_COOLDOWN,
+};
+use crate::{ WINDOW_HEIGHT, WINDOW_WIDTH };
+
+/// Lane width used when the file doesn't set `road.lane_width`.
+pub const DEFAULT_LANE_WIDTH: i32 = 50;
+
+/// Intersection layout and simulation parameters, usually loaded from a TOML file:
+///
+/// ```toml
+/// spawn_cooldown = 0.5 # seconds
+/// safe_distance = 1.0 # meters
+///
+/// [units]
+/// pixels_per_meter = 10.0
+/// tick_duration = 0.016 # seconds of simulated time per update
+///
+/// [window]
+/// width = 1024
+/// height = 768
+///
+/// [road]
+/// lane_width = 50 # pixels
+///
+/// # Velocities and accelerations of cars
+/// [velocities] # km/h
+/// slow = 15
+/// normal = 30
+/// fast = 45
+///
+/// [acceleration] # meters per second squared
+/// max = 2.5
+/// comfortable_deceleration = 3.5
+/// emergency_deceleration = 8.0
+///
+/// # Relative frequency of each kind of vehicle spawned at random (car, motorbike, bus, truck,
+/// # emergency)
+/// [traffic_mix]
+/// car = 80
+/// bus = 20
+///
+/// # Changes to the built-in vehicle types, with the velocities and accelerations above
+/// [vehicle_types.bus]
+/// length = 12.0 # meters
+/// width = 4.5
+/// velocities = { slow = 10, normal = 25, fast = 35 }
+/// acceleration = { max = 1.2 }
+/// sprite = "ship" # assets/sprites/ship_<north|west|south|est>.png
+///
+/// # Model followed on the approach lanes: "rules" or "idm" (Intelligent Driver Model,
+/// # using the fast velocity, safe distance, maximum acceleration and comfortable deceleration)
+/// [car_following]
+/// model = "idm"
+/// time_headway = 1.0 # seconds
+///
+/// # Pedestrians crossing at the crosswalks
+/// [pedestrians]
+/// interval = 4.0 # seconds between two pedestrians, 0 for none
+/// walking_speed = 1.4 # meters per second
+///
+/// # Grid of intersections, each in an area the size of the window, with the policy of
+/// # each one, row by row (the others use the policy chosen at startup)
+/// [network]
+/// rows = 2
+/// columns = 3
+/// policies = ["traffic-light", "reservation"]
+///
+/// # Close call thresholds: TTC and PET in seconds, DRAC in meters per second squared
+/// [safety]
+/// ttc = 1.5
+/// pet = 1.0
+/// drac = 4.0
+///
+/// # Lanes are listed from the outer edge of the road (right-most lane first), each with
+/// # the manoeuvre, or list of manoeuvres, permitted from it
+/// [[approaches]]
+/// road = "north"
+/// lanes = ["right", "straight", ["straight", "left"]]
+/// ```
+///
+/// Every key is optional and falls back to the built-in value. Without any `approaches`
+/// the four default three-lane approaches are used.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub geometry: Geometry,
+    pub vehicle_types: VehicleCatalog,
+    pub traffic_mix: TrafficMix,
+    pub spawn_cooldown: Duration,
+    /// Simulated time elapsed per update.
+    pub tick_duration: Duration,
+    pub safety: SafetyThresholds,
+    pub pedestrians: PedestrianTraffic,
+    pub network: NetworkLayout,
+    /// Lines of every approach, one `Vec` per approach.
+    pub approaches: Vec<Vec<Line>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            geometry: Geometry::default(),
+            vehicle_types: VehicleCatalog::default(),
+            traffic_mix: TrafficMix::default(),
+            spawn_cooldown: SPAWN_COOLDOWN,
+            tick_duration: TICK_DURATION,
+            safety: SafetyThresholds::default(),
+            pedestrians: PedestrianTraffic::default(),
+            network: NetworkLayout::default(),
+            approaches: default_approaches(),
+        }
+    }
+}
+
+fn default_approaches() -> Vec<Vec<Line>> {
+    [RoadDirection::North, RoadDirection::West, RoadDirection::South, RoadDirection::East]
+        .into_iter()
+        .map(Line::approach)
+        .collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    spawn_cooldown: Option<f64>,
+    safe_distance: Option<f64>,
+    #[serde(default)]
+    units: RawUnits,
+    #[serde(default)]
+    window: RawWindow,
+    #[serde(default)]
+    road: RawRoad,
+    #[serde(default)]
+    velocities: RawVelocities,
+    #[serde(default)]
+    acceleration: RawAcceleration,
+    #[serde(default)]
+    car_following: RawCarFollowing,
+    #[serde(default)]
+    safety: RawSafety,
+    #[serde(default)]
+    pedestrians: RawPedestrians,
+    #[serde(default)]
+    network: RawNetwork,
+    traffic_mix: Option<BTreeMap<VehicleKind, f64>>,
+    #[serde(default)]
+    vehicle_types: BTreeMap<VehicleKind, RawVehicleType>,
+    approaches: Option<Vec<RawApproach>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawUnits {
+    pixels_per_meter: Option<f64>,
+    tick_duration: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawWindow {
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRoad {
+    lane_width: Option<i32>,
+}
+
+/// Velocities in km/h.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct RawVelocities {
+    slow: Option<f64>,
+    normal: Option<f64>,
+    fast: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct RawAcceleration {
+    max: Option<f64>,
+    comfortable_deceleration: Option<f64>,
+    emergency_deceleration: Option<f64>,
+}
+
+impl RawVelocities {
+    /// `base` with the velocities, accelerations and safe distance that are set replaced.
+    pub(super) fn profile(
+        &self,
+        base: DrivingProfile,
+        acceleration: &RawAcceleration,
+        safe_distance: Option<f64>
+    ) -> DrivingProfile {
+        DrivingProfile {
+            slow: self.slow.map_or(base.slow, meters_per_second),
+            normal: self.normal.map_or(base.normal, meters_per_second),
+            fast: self.fast.map_or(base.fast, meters_per_second),
+            safe_distance: safe_distance.unwrap_or(base.safe_distance),
+            max_acceleration: acceleration.max.unwrap_or(base.max_acceleration),
+            comfortable_deceleration: acceleration.comfortable_deceleration.unwrap_or(
+                base.comfortable_deceleration
+            ),
+            emergency_deceleration: acceleration.emergency_deceleration.unwrap_or(
+                base.emergency_deceleration
+            ),
+            ..base
+        }
+    }
+}
+
+/// Report the inconsistent fields of `profile`, named after the keys found under `prefix`.
+pub(super) fn check_profile(profile: &DrivingProfile, prefix: &str, errors: &mut Vec<String>) {
+    for (field, value) in [
+        ("velocities.slow", profile.slow),
+        ("acceleration.max", profile.max_acceleration),
+        ("acceleration.comfortable_deceleration", profile.comfortable_deceleration),
+        ("acceleration.emergency_deceleration", profile.emergency_deceleration),
+    ] {
+        if !value.is_finite() || value <= 0.0 {
+            errors.push(format!("`{}{}` must be positive (got {})", prefix, field, value));
+        }
+    }
+    if profile.normal < profile.slow {
+        errors.push(
+            format!(
+                "`{0}velocities.normal` must be at least `{0}velocities.slow` (got {1:.1} < {2:.1} km/h)",
+                prefix,
+                kmh(profile.normal),
+                kmh(profile.slow)
+            )
+        );
+    }
+    if profile.fast < profile.normal {
+        errors.push(
+            format!(
+                "`{0}velocities.fast` must be at least `{0}velocities.normal` (got {1:.1} < {2:.1} km/h)",
+                prefix,
+                kmh(profile.fast),
+                kmh(profile.normal)
+            )
+        );
+    }
+    if profile.emergency_deceleration < profile.comfortable_deceleration {
+        errors.push(
+            format!(
+                "`{0}acceleration.emergency_deceleration` must be at least `{0}acceleration.comfortable_deceleration` (got {1} < {2})",
+                prefix,
+                profile.emergency_deceleration,
+                profile.comfortable_deceleration
+            )
+        );
+    }
+    if !profile.safe_distance.is_finite() || profile.safe_distance < 0.0 {
+        errors.push(
+            format!(
+                "`{}safe_distance` must not be negative (got {})",
+                prefix,
+                profile.safe_distance
+            )
+        );
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCarFollowing {
+    model: Option<CarFollowing>,
+    time_headway: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawVehicleType {
+    length: Option<f64>,
+    width: Option<f64>,
+    #[serde(default)]
+    velocities: RawVelocities,
+    #[serde(default)]
+    acceleration: RawAcceleration,
+    sprite: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSafety {
+    ttc: Option<f64>,
+    pet: Option<f64>,
+    drac: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPedestrians {
+    interval: Option<f64>,
+    walking_speed: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawNetwork {
+    rows: Option<usize>,
+    columns: Option<usize>,
+    policies: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawApproach {
+    road: RoadDirection,
+    lanes: Vec<RawLane>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawLane {
+    One(Direction),
+    Many(Vec<Direction>),
+}
+
+impl RawLane {
+    fn directions(&self) -> Vec<Direction> {
+        match self {
+            RawLane::One(direction) => vec![*direction],
+            RawLane::Many(directions) => directions.clone(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e|
+            format!("cannot read config file '{}': {}", path.display(), e)
+        )?;
+        Config::from_toml(&text).map_err(|e| format!("invalid config file '{}': {}", path.display(), e))
+    }
+
+    /// Parse and validate a configuration. Every invalid field is reported in the error.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let raw: RawConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        let default = Config::default();
+        let mut errors = vec![];
+
+        let car = default.vehicle_types.get(VehicleKind::Car).profile;
+        let mut profile = raw.velocities.profile(
+            car,
+            &raw.acceleration,
+            raw.safe_distance
+        );
+        check_profile(&profile, "", &mut errors);
+        profile.car_following = raw.car_following.model.unwrap_or(car.car_following);
+        match raw.car_following.time_headway {
+            None => (),
+            Some(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                profile.time_headway = seconds;
+            }
+            Some(seconds) => {
+                errors.push(
+                    format!(
+                        "`car_following.time_headway` must be a positive number of seconds (got {})",
+                        seconds
+                    )
+                );
+            }
+        }
+
+        let mut vehicle_types = VehicleCatalog::new(&profile);
+        for (kind, raw_type) in &raw.vehicle_types {
+            let prefix = format!("vehicle_types.{}.", kind.name());
+            let vehicle_type = vehicle_types.get_mut(*kind);
+            vehicle_type.length = raw_type.length.unwrap_or(vehicle_type.length);
+            vehicle_type.width = raw_type.width.unwrap_or(vehicle_type.width);
+            for (field, value) in [("length", vehicle_type.length), ("width", vehicle_type.width)] {
+                if !value.is_finite() || value <= 0.0 {
+                    errors.push(format!("`{}{}` must be positive (got {})", prefix, field, value));
+                }
+            }
+            vehicle_type.profile = raw_type.velocities.profile(
+                vehicle_type.profile,
+                &raw_type.acceleration,
+                None
+            );
+            check_profile(&vehicle_type.profile, &prefix, &mut errors);
+            if let Some(sprite) = &raw_type.sprite {
+                vehicle_type.sprite = sprite.clone();
+            }
+        }
+
+        let traffic_mix = match raw.traffic_mix {
+            None => default.traffic_mix,
+            Some(weights) => {
+                for (kind, weight) in &weights {
+                    if !weight.is_finite() || *weight < 0.0 {
+                        errors.push(
+                            format!(
+                                "`traffic_mix.{}` must be a positive number (got {})",
+                                kind.name(),
+                                weight
+                            )
+                        );
+                    }
+                }
+                if !weights.values().any(|weight| *weight > 0.0) {
+                    errors.push("`traffic_mix` must give a positive weight to one kind".to_string());
+                }
+                TrafficMix::new(weights)
+            }
+        };
+
+        let spawn_cooldown = match raw.spawn_cooldown {
+            None => default.spawn_cooldown,
+            Some(seconds) => match Duration::try_from_secs_f64(seconds) {
+                Ok(duration) => duration,
+                Err(_) => {
+                    errors.push(
+                        format!(
+                            "`spawn_cooldown` must be a positive number of seconds (got {})",
+                            seconds
+                        )
+                    );
+                    default.spawn_cooldown
+                }
+            },
+        };
+
+        let tick_duration = match raw.units.tick_duration {
+            None => default.tick_duration,
+            Some(seconds) => match Duration::try_from_secs_f64(seconds) {
+                Ok(duration) if !duration.is_zero() => duration,
+                _ => {
+                    errors.push(
+                        format!(
+                            "`units.tick_duration` must be a positive number of seconds (got {})",
+                            seconds
+                        )
+                    );
+                    default.tick_duration
+                }
+            },
+        };
+        let pixels_per_meter = raw.units.pixels_per_meter.unwrap_or(DEFAULT_PIXELS_PER_METER);
+        if !pixels_per_meter.is_finite() || pixels_per_meter <= 0.0 {
+            errors.push(
+                format!("`units.pixels_per_meter` must be positive (got {})", pixels_per_meter)
+            );
+        }
+
+        let safety = SafetyThresholds {
+            ttc: raw.safety.ttc.unwrap_or(default.safety.ttc),
+            pet: raw.safety.pet.unwrap_or(default.safety.pet),
+            drac: raw.safety.drac.unwrap_or(default.safety.drac),
+        };
+        for (field, value) in [
+            ("safety.ttc", safety.ttc),
+            ("safety.pet", safety.pet),
+            ("safety.drac", safety.drac),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                errors.push(format!("`{}` must be a positive number (got {})", field, value));
+            }
+        }
+
+        let interval = match raw.pedestrians.interval {
+            None => default.pedestrians.interval,
+            Some(0.0) => None,
+            Some(seconds) => match Duration::try_from_secs_f64(seconds) {
+                Ok(duration) if !duration.is_zero() => Some(duration),
+                _ => {
+                    errors.push(
+                        format!(
+                            "`pedestrians.interval` must be a positive number of seconds (got {})",
+                            seconds
+                        )
+                    );
+                    default.pedestrians.interval
+                }
+            },
+        };
+        let walking_speed = raw.pedestrians.walking_speed.unwrap_or(
+            default.pedestrians.walking_speed
+        );
+        if !walking_speed.is_finite() || walking_speed <= 0.0 {
+            errors.push(
+                format!("`pedestrians.walking_speed` must be positive (got {})", walking_speed)
+            );
+        }
+        let pedestrians = PedestrianTraffic { interval, walking_speed };
+
+        let network = NetworkLayout {
+            rows: raw.network.rows.unwrap_or(default.network.rows),
+            columns: raw.network.columns.unwrap_or(default.network.columns),
+            policies: raw.network.policies.unwrap_or(default.network.policies),
+        };
+        for (field, size) in [("network.rows", network.rows), ("network.columns", network.columns)] {
+            if size == 0 {
+                errors.push(format!("`{}` must be at least 1", field));
+            }
+        }
+        if network.policies.len() > network.rows * network.columns {
+            errors.push(
+                format!(
+                    "`network.policies` lists {} policies for {} intersections",
+                    network.policies.len(),
+                    network.rows * network.columns
+                )
+            );
+        }
+        for (i, name) in network.policies.iter().enumerate() {
+            if !POLICY_NAMES.contains(&name.as_str()) {
+                errors.push(
+                    format!(
+                        "`network.policies[{}]`: unknown policy '{}' (expected one of: {})",
+                        i,
+                        name,
+                        POLICY_NAMES.join(", ")
+                    )
+                );
+            }
+        }
+
+        let approaches = match raw.approaches {
+            None => default.approaches,
+            Some(raw_approaches) => {
+                let mut approaches: Vec<Vec<Line>> = vec![];
+                for (i, approach) in raw_approaches.iter().enumerate() {
+                    if raw_approaches[..i].iter().any(|other| other.road == approach.road) {
+                        errors.push(
+                            format!("`approaches[{}].road`: {:?} is defined twice", i, approach.road)
+                        );
+                    } else if approach.lanes.is_empty() {
+                        errors.push(format!("`approaches[{}].lanes` must not be empty", i));
+                    } else {
+                        let mut lines = vec![];
+                        for (lane, raw_lane) in approach.lanes.iter().enumerate() {
+                            let directions = raw_lane.directions();
+                            let has_duplicates = directions
+                                .iter()
+                                .enumerate()
+                                .any(|(j, direction)| directions[..j].contains(direction));
+                            if directions.is_empty() || has_duplicates {
+                                errors.push(
+                                    format!(
+                                        "`approaches[{}].lanes[{}]` must list distinct manoeuvres (got {:?})",
+                                        i,
+                                        lane,
+                                        directions
+                                    )
+                                );
+                            }
+                            lines.push(Line::new(approach.road, lane as i32, directions));
+                        }
+                        approaches.push(lines);
+                    }
+                }
+                if raw_approaches.is_empty() {
+                    errors.push("`approaches` must define at least one approach".to_string());
+                }
+                approaches
+            }
+        };
+
+        let lanes_per_approach = approaches.iter().map(Vec::len).max().unwrap_or(1) as i32;
+        let geometry = Geometry::centered(
+            raw.window.width.unwrap_or(WINDOW_WIDTH),
+            raw.window.height.unwrap_or(WINDOW_HEIGHT),
+            raw.road.lane_width.unwrap_or(DEFAULT_LANE_WIDTH),
+            lanes_per_approach,
+            pixels_per_meter
+        );
+        let widest = vehicle_types
+            .iter()
+            .map(|vehicle_type| vehicle_type.width)
+            .fold(0.0, f64::max);
+        if pixels_per_meter > 0.0 && geometry.to_meters(geometry.lane_width) < widest {
+            errors.push(
+                format!(
+                    "`road.lane_width` must be at least {} pixels, the width of the widest vehicle at {} pixels per meter (got {})",
+                    geometry.to_pixels(widest).ceil(),
+                    pixels_per_meter,
+                    geometry.lane_width
+                )
+            );
+        }
+        for (field, size) in [
+            ("window.width", geometry.window_width),
+            ("window.height", geometry.window_height),
+        ] {
+            if size < geometry.road_width() {
+                errors.push(
+                    format!(
+                        "`{}` is too small for a {}-lane road {} pixels wide (got {})",
+                        field,
+                        2 * lanes_per_approach,
+                        geometry.road_width(),
+                        size
+                    )
+                );
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        Ok(Config {
+            geometry,
+            vehicle_types,
+            traffic_mix,
+            spawn_cooldown,
+            tick_duration,
+            safety,
+            pedestrians,
+            network,
+            approaches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(text: &str) -> String {
+        Config::from_toml(text).expect_err("the configuration should be rejected")
+    }
+
+    #[test]
+    fn empty_file_gives_the_defaults() {
+        let config = Config::from_toml("").unwrap();
+        let default = Config::default();
+        assert_eq!(config.geometry.lane_width, default.geometry.lane_width);
+        assert_eq!(config.geometry.lanes_per_approach, default.geometry.lanes_per_approach);
+        assert_eq!(config.vehicle_types, default.vehicle_types);
+        assert_eq!(config.tick_duration, default.tick_duration);
+        assert_eq!(config.approaches, default.approaches);
+    }
+
+    #[test]
+    fn rejects_malformed_toml() {
+        assert!(Config::from_toml("spawn_cooldown = ").is_err());
+        assert!(Config::from_toml("spawn_cooldown = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let error = rejection(
+            "spawn_cooldown = -1\n[units]\ntick_duration = 0\npixels_per_meter = 0\n"
+        );
+        assert!(error.contains("`spawn_cooldown`"), "{}", error);
+        assert!(error.contains("`units.tick_duration`"), "{}", error);
+        assert!(error.contains("`units.pixels_per_meter`"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        assert!(rejection("spawn_cooldown = 1e30").contains("`spawn_cooldown`"));
+        assert!(rejection("[units]\ntick_duration = nan").contains("`units.tick_duration`"));
+    }
+
+    #[test]
+    fn rejects_inconsistent_velocities() {
+        let error = rejection("[velocities]\nslow = 40\nnormal = 30\n");
+        let expected = "`velocities.normal` must be at least `velocities.slow`";
+        assert!(error.contains(expected), "{}", error);
+    }
+
+    #[test]
+    fn rejects_approaches_without_lanes() {
+        let error = rejection("[[approaches]]\nroad = \"north\"\nlanes = []\n");
+        assert!(error.contains("`approaches[0].lanes` must not be empty"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_invalid_networks() {
+        let error = rejection("[network]\nrows = 0\npolicies = [\"green-wave\"]\n");
+        assert!(error.contains("`network.rows` must be at least 1"), "{}", error);
+        assert!(error.contains("unknown policy 'green-wave'"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_inconsistent_velocities_of_a_vehicle_type() {
+        let error = rejection("[vehicle_types.bus]\nvelocities = { fast = 10 }\n");
+        assert!(error.contains("`vehicle_types.bus.velocities.fast`"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_a_negative_pedestrian_interval() {
+        assert!(rejection("[pedestrians]\ninterval = -2").contains("`pedestrians.interval`"));
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use super::{
+    policy::reactive::reactive_velocity,
+    reservation::MAX_PLAN_TICKS,
+    roads::{ RoadDirection, RoadIntersection },
+    vehicle::Vehicle,
+    vehicle_type::VehicleKind,
+};
+
+/// Shares of its velocity tried in turn by an emergency vehicle (or its escort) in the
+/// intersection whose path is not clear, before it stops to let the other vehicles by.
+const CLEARANCE_VELOCITY_SHARES: [f64; 4] = [1.0, 0.75, 0.5, 0.25];
+
+/// Right of way of emergency vehicles, enforced on top of any `IntersectionPolicy`.
+///
+/// The first emergency vehicle that hasn't left the intersection yet preempts it: vehicles
+/// of the other approaches that can still stop comfortably before the intersection are held
+/// at its edge, while the emergency vehicle and the vehicles ahead of it in its lane (its
+/// escort) cross on the car-following rules once the vehicles of the other approaches that
+/// are in (or committed to) the intersection have left it. Their path is checked again every
+/// tick until they are through: they slow down, or stop, to keep clear of those vehicles. The
+/// preemption lasts until the emergency vehicle and its escort have left the intersection.
+#[derive(Debug, Default)]
+pub struct EmergencyPreemption {
+    /// Emergency vehicle with the right of way, and its approach.
+    pub active: Option<(i32, RoadDirection)>,
+    /// Vehicles let through ahead of it.
+    pub escort: Vec<i32>,
+    /// Vehicles held clear of its path.
+    pub held: Vec<i32>,
+}
+
+impl EmergencyPreemption {
+    pub fn is_holding(&self, vehicle_id: i32) -> bool {
+        self.held.contains(&vehicle_id)
+    }
+
+    /// Override the `velocities` the policy gave to `vehicles` for a tick of `dt`.
+    pub fn apply(
+        &mut self,
+        vehicles: &[Vehicle],
+        velocities: &mut [f64],
+        intersection: &RoadIntersection,
+        dt: Duration
+    ) {
+        let is_before_exit = |vehicle: &Vehicle| {
+            vehicle.distance_to_intersection(intersection).is_some() ||
+                intersection.overlaps(vehicle)
+        };
+        let is_preempting = vehicles.iter().any(|vehicle| {
+            (self.active.is_some_and(|(id, _)| id == vehicle.id) ||
+                self.escort.contains(&vehicle.id)) &&
+                is_before_exit(vehicle)
+        });
+        if !is_preempting {
+            self.active = vehicles
+                .iter()
+                .filter(|vehicle| vehicle.kind == VehicleKind::Emergency && is_before_exit(vehicle))
+                .min_by_key(|vehicle| vehicle.id)
+                .map(|vehicle| (vehicle.id, vehicle.road_direction));
+            self.escort.clear();
+            self.held.clear();
+        }
+        let Some((emergency_id, approach)) = self.active else {
+            return;
+        };
+
+        // Held vehicles stay held, even once too close to the edge to stop comfortably
+        let emergency = vehicles.iter().find(|vehicle| vehicle.id == emergency_id);
+        for vehicle in vehicles {
+            let Some(distance) = vehicle.distance_to_intersection(intersection) else {
+                continue;
+            };
+            if vehicle.road_direction == approach {
+                if
+                    emergency.is_some_and(|emergency| emergency.gap_to(vehicle).is_some()) &&
+                    !self.escort.contains(&vehicle.id)
+                {
+                    self.escort.push(vehicle.id);
+                }
+            } else if
+                !self.held.contains(&vehicle.id) &&
+                distance >= vehicle.stopping_distance(vehicle.profile.comfortable_deceleration)
+            {
+                self.held.push(vehicle.id);
+            }
+        }
+
+        // Vehicles of the other approaches crossing whatever happens, with their velocity
+        let crossing: Vec<(Vehicle, f64)> = vehicles
+            .iter()
+            .zip(velocities.iter())
+            .filter(|(vehicle, _)| {
+                vehicle.id != emergency_id &&
+                    !self.escort.contains(&vehicle.id) &&
+                    vehicle.road_direction != approach &&
+                    !self.held.contains(&vehicle.id) &&
+                    is_before_exit(vehicle)
+            })
+            .map(|(vehicle, &velocity)| (vehicle.clone(), velocity))
+            .collect();
+
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            if self.held.contains(&vehicle.id) {
+                velocities[i] = velocities[i].min(vehicle.velocity_to_stop_at(intersection, dt));
+                continue;
+            }
+            if vehicle.id != emergency_id && !self.escort.contains(&vehicle.id) {
+                continue;
+            }
+            let velocity = reactive_velocity(vehicles, i, intersection, dt);
+            velocities[i] = if vehicle.distance_to_intersection(intersection).is_some() {
+                // Enters once the conflicting vehicles are out of the intersection
+                if vehicle.is_at_stop_line(intersection, velocity, dt) && !crossing.is_empty() {
+                    velocity.min(vehicle.velocity_to_stop_at(intersection, dt))
+                } else {
+                    velocity
+                }
+            } else if intersection.overlaps(vehicle) {
+                CLEARANCE_VELOCITY_SHARES.iter()
+                    .map(|share| velocity * share)
+                    .find(|&velocity| is_path_clear(vehicle, velocity, &crossing, intersection, dt))
+                    .unwrap_or(0.0)
+            } else {
+                velocity
+            };
+        }
+    }
+}
+
+/// Whether `vehicle` aiming for `velocity` gets through the intersection within the planning
+/// horizon keeping its safe distance to the `crossing` vehicles, each aiming for its own
+/// velocity.
+fn is_path_clear(
+    vehicle: &Vehicle,
+    velocity: f64,
+    crossing: &[(Vehicle, f64)],
+    intersection: &RoadIntersection,
+    dt: Duration
+) -> bool {
+    if crossing.is_empty() {
+        return true;
+    }
+    let margin = 2.0 * vehicle.profile.safe_distance;
+    let mut ghost = vehicle.clone();
+    let mut others = crossing.to_vec();
+    for _ in 0..MAX_PLAN_TICKS {
+        ghost.accelerate(velocity, dt);
+        ghost.step(dt);
+        for (other, velocity) in &mut others {
+            other.accelerate(*velocity, dt);
+            other.step(dt);
+        }
+        let mut body = ghost.clone();
+        body.length += margin;
+        body.width += margin;
+        if others.iter().any(|(other, _)| body.overlaps(other)) {
+            return false;
+        }
+        if ghost.distance_to_intersection(intersection).is_none() && !intersection.overlaps(&ghost) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        config::Config,
+        roads::{ Direction, Line },
+        testing::{ car, spawn },
+        vehicles_management::VehiclesManagement,
+    };
+
+    fn line(road_direction: RoadDirection) -> Line {
+        Line::new(road_direction, 1, vec![Direction::Straight])
+    }
+
+    /// Run the default policy with a car from West spawned first, and an emergency vehicle
+    /// from North `delay` ticks later, and call `observe` with the simulation every tick
+    /// until both have left.
+    fn cross_paths(delay: u64, mut observe: impl FnMut(&VehiclesManagement)) {
+        let config = Config::default();
+        let mut vehicles = VehiclesManagement::with_config(0, &config);
+        vehicles.spawn_cooldown = Duration::ZERO;
+        vehicles.spawn_on(&line(RoadDirection::West), Direction::Straight, VehicleKind::Car);
+        while vehicles.clock.tick < delay {
+            vehicles.update();
+        }
+        vehicles.spawn_on(&line(RoadDirection::North), Direction::Straight, VehicleKind::Emergency);
+        while !vehicles.list.is_empty() {
+            assert!(vehicles.clock.tick < 5000, "the vehicles never left");
+            vehicles.update();
+            let tick = vehicles.clock.tick;
+            assert!(vehicles.collisions.crashes.is_empty(), "crash at tick {}", tick);
+            observe(&vehicles);
+        }
+    }
+
+    #[test]
+    fn vehicles_of_other_approaches_are_held_for_the_emergency_vehicle() {
+        // The car is far enough to stop when the emergency vehicle shows up
+        let mut held = false;
+        let mut order = vec![];
+        cross_paths(1, |vehicles| {
+            held |= vehicles.emergency.is_holding(0);
+            for vehicle in &vehicles.list {
+                if vehicles.intersection.overlaps(vehicle) && !order.contains(&vehicle.id) {
+                    order.push(vehicle.id);
+                }
+            }
+        });
+        assert!(held);
+        assert_eq!(order, [1, 0]);
+    }
+
+    #[test]
+    fn emergency_vehicle_waits_for_the_intersection_to_clear() {
+        // The car is already crossing when the emergency vehicle reaches the intersection
+        let mut waited = false;
+        cross_paths(150, |vehicles| {
+            let car = vehicles.list.iter().find(|vehicle| vehicle.id == 0);
+            let emergency = vehicles.list.iter().find(|vehicle| vehicle.id == 1);
+            if let (Some(car), Some(emergency)) = (car, emergency) {
+                if vehicles.stop_area.overlaps(car) {
+                    assert!(
+                        emergency.distance_to_intersection(&vehicles.stop_area).is_some(),
+                        "the emergency vehicle entered at tick {}",
+                        vehicles.clock.tick
+                    );
+                    waited |= emergency.velocity < emergency.profile.normal;
+                }
+            }
+        });
+        assert!(waited);
+    }
+
+    #[test]
+    fn path_is_not_clear_when_it_is_not_through_within_the_horizon() {
+        let config = Config::default();
+        let intersection = config.geometry.stop_area();
+        let dt = config.tick_duration;
+        let mut emergency = spawn(
+            0,
+            &config,
+            VehicleKind::Emergency,
+            RoadDirection::North,
+            Direction::Straight
+        );
+        emergency.y = intersection.y + intersection.height / 2.0;
+        let crossing = [(car(1, &config, RoadDirection::West, Direction::Straight), 0.0)];
+        assert!(!is_path_clear(&emergency, 0.0, &crossing, &intersection, dt));
+        assert!(is_path_clear(&emergency, emergency.velocity, &crossing, &intersection, dt));
+    }
+}
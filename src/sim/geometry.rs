@@ -0,0 +1,397 @@
+use std::f64::consts::FRAC_PI_2;
+use serde::{ Deserialize, Serialize };
+
+use super::{
+    roads::{ Crosswalk, Direction, Line, RoadDirection, RoadIntersection, CROSSWALK_WIDTH },
+    units::DEFAULT_PIXELS_PER_METER,
+    vehicle::Vehicle,
+};
+use crate::{ WINDOW_HEIGHT, WINDOW_WIDTH };
+
+/// Smallest turn radius, in wheelbases of the turning vehicle: on a tighter turn the ends of
+/// its body swing out of its lane.
+const MIN_TURN_RADIUS_PER_WHEELBASE: f64 = 0.75;
+
+/// Layout of the crossroads, in pixels of the window, and the scale of the world the
+/// simulation runs in: every position used by the simulation (spawn points, turn paths,
+/// intersection box) is derived from it in meters, every position drawn by the renderer
+/// in pixels.
+///
+/// Each road carries `lanes_per_approach` lanes in each direction, vehicles drive on the
+/// right, and lanes are counted from the outer edge of the road: lane 0 is the right-most
+/// lane of an approach.
+///
+/// The intersection sits in an area the size of the window, where vehicles spawn and which
+/// they leave by: the window itself for a single intersection, one cell of the grid in a
+/// `Network`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Geometry {
+    pub window_width: i32,
+    pub window_height: i32,
+    /// Top-left corner of the area of the intersection.
+    pub area_x: i32,
+    pub area_y: i32,
+    pub lane_width: i32,
+    pub lanes_per_approach: i32,
+    /// Top-left corner of the intersection.
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub pixels_per_meter: f64,
+}
+
+impl Geometry {
+    /// Layout with the intersection in the middle of the window.
+    pub fn centered(
+        window_width: i32,
+        window_height: i32,
+        lane_width: i32,
+        lanes_per_approach: i32,
+        pixels_per_meter: f64
+    ) -> Self {
+        let road_width = 2 * lanes_per_approach * lane_width;
+        Geometry {
+            window_width,
+            window_height,
+            area_x: 0,
+            area_y: 0,
+            lane_width,
+            lanes_per_approach,
+            origin_x: (window_width - road_width) / 2,
+            origin_y: (window_height - road_width) / 2,
+            pixels_per_meter,
+        }
+    }
+
+    /// Same layout moved `row` areas down and `column` areas right, for the intersection at
+    /// that place of a grid.
+    pub fn at(&self, row: usize, column: usize) -> Self {
+        let (dx, dy) = (column as i32 * self.window_width, row as i32 * self.window_height);
+        Geometry {
+            area_x: self.area_x + dx,
+            area_y: self.area_y + dy,
+            origin_x: self.origin_x + dx,
+            origin_y: self.origin_y + dy,
+            ..*self
+        }
+    }
+
+    pub fn to_meters(&self, pixels: i32) -> f64 {
+        (pixels as f64) / self.pixels_per_meter
+    }
+
+    pub fn to_pixels(&self, meters: f64) -> f64 {
+        meters * self.pixels_per_meter
+    }
+
+    /// Width of a road, both ways included.
+    pub fn road_width(&self) -> i32 {
+        2 * self.lanes_per_approach * self.lane_width
+    }
+
+    pub fn intersection(&self) -> RoadIntersection {
+        RoadIntersection::new(
+            self.to_meters(self.origin_x),
+            self.to_meters(self.origin_y),
+            self.to_meters(self.road_width()),
+            self.to_meters(self.road_width())
+        )
+    }
+
+    /// Intersection box and the crosswalks around it, in meters: vehicles wait for the
+    /// right of way at its edge.
+    pub fn stop_area(&self) -> RoadIntersection {
+        let RoadIntersection { x, y, width, height } = self.intersection();
+        RoadIntersection::new(
+            x - CROSSWALK_WIDTH,
+            y - CROSSWALK_WIDTH,
+            width + 2.0 * CROSSWALK_WIDTH,
+            height + 2.0 * CROSSWALK_WIDTH
+        )
+    }
+
+    /// Crosswalk over the road of the `road` approach, along the intersection edge.
+    pub fn crosswalk(&self, road: RoadDirection) -> Crosswalk {
+        let RoadIntersection { x, y, width, height } = self.intersection();
+        let (x, y, width, height) = match road {
+            RoadDirection::North => (x, y - CROSSWALK_WIDTH, width, CROSSWALK_WIDTH),
+            RoadDirection::West => (x + width, y, CROSSWALK_WIDTH, height),
+            RoadDirection::South => (x, y + height, width, CROSSWALK_WIDTH),
+            RoadDirection::East => (x - CROSSWALK_WIDTH, y, CROSSWALK_WIDTH, height),
+        };
+        Crosswalk { road, x, y, width, height }
+    }
+
+    /// Coordinate across the road (x for North/South, y for West/East) of the center of
+    /// `lane` of `road_direction`, in pixels.
+    pub fn lane_center(&self, road_direction: RoadDirection, lane: i32) -> i32 {
+        let road_width = self.road_width();
+        let half = self.lane_width / 2;
+        match road_direction {
+            RoadDirection::North => self.origin_x + lane * self.lane_width + half,
+            RoadDirection::South =>
+                self.origin_x + road_width - (lane + 1) * self.lane_width + half,
+            RoadDirection::West => self.origin_y + lane * self.lane_width + half,
+            RoadDirection::East =>
+                self.origin_y + road_width - (lane + 1) * self.lane_width + half,
+        }
+    }
+
+    /// Lane of `road_direction` whose center is the closest to `across`, the coordinate
+    /// across the road (as in `lane_center`) in meters.
+    pub fn lane_at(&self, road_direction: RoadDirection, across: f64) -> i32 {
+        let offset = |lane: &i32| {
+            (self.to_meters(self.lane_center(road_direction, *lane)) - across).abs()
+        };
+        (0..self.lanes_per_approach)
+            .min_by(|a, b| offset(a).total_cmp(&offset(b)))
+            .unwrap_or(0)
+    }
+
+    /// Where the center of a vehicle `length` meters long enters the area on `line`, in
+    /// meters.
+    pub fn spawn_position(&self, line: &Line, length: f64) -> (f64, f64) {
+        let lane = self.to_meters(self.lane_center(line.road_direction, line.lane));
+        let half = length / 2.0;
+        let (left, top) = (self.to_meters(self.area_x), self.to_meters(self.area_y));
+        match line.road_direction {
+            RoadDirection::North => (lane, top + half),
+            RoadDirection::West => (left + self.to_meters(self.window_width) - half, lane),
+            RoadDirection::South => (lane, top + self.to_meters(self.window_height) - half),
+            RoadDirection::East => (left + half, lane),
+        }
+    }
+
+    /// Quarter circle followed by the axles, `wheelbase` meters apart, of a vehicle of `line`
+    /// taking `direction` through the intersection, `None` when going straight. The arc is
+    /// tangent to the lane of the approach where it enters the intersection, and to the lane
+    /// with the same index on the road turned into, unless that is too tight for the
+    /// wheelbase: the turn then starts before the intersection and ends past it.
+    pub fn turn_path(&self, line: &Line, direction: Direction, wheelbase: f64) -> Option<TurnPath> {
+        let sign = match direction {
+            Direction::Straight => {
+                return None;
+            }
+            Direction::Left => -1.0,
+            Direction::Right => 1.0,
+        };
+        let exit = line.road_direction.after_turn(direction);
+        let lane = self.to_meters(self.lane_center(line.road_direction, line.lane));
+        let corner = self.to_meters(self.lane_center(exit, line.lane));
+        let intersection = self.intersection();
+        let (top, left) = (intersection.y, intersection.x);
+        let (bottom, right) = (top + intersection.height, left + intersection.width);
+        let (radius, start, end) = match line.road_direction {
+            RoadDirection::North => {
+                let radius = corner - top;
+                (radius, (lane, top), (lane - sign * radius, corner))
+            }
+            RoadDirection::South => {
+                let radius = bottom - corner;
+                (radius, (lane, bottom), (lane + sign * radius, corner))
+            }
+            RoadDirection::West => {
+                let radius = right - corner;
+                (radius, (right, lane), (corner, lane - sign * radius))
+            }
+            RoadDirection::East => {
+                let radius = corner - left;
+                (radius, (left, lane), (corner, lane + sign * radius))
+            }
+        };
+        let extra = (wheelbase * MIN_TURN_RADIUS_PER_WHEELBASE - radius).max(0.0);
+        let (sin, cos) = line.road_direction.heading().to_radians().sin_cos();
+        let start = (start.0 - extra * cos, start.1 - extra * sin);
+        let (sin, cos) = exit.heading().to_radians().sin_cos();
+        let end = (end.0 + extra * cos, end.1 + extra * sin);
+        let radius = radius + extra;
+        Some(TurnPath {
+            start,
+            end,
+            radius,
+            heading: line.road_direction.heading(),
+            sign,
+        })
+    }
+
+    /// Whether `vehicle` is still (at least partly) in the area of the intersection.
+    pub fn is_on_screen(&self, vehicle: &Vehicle) -> bool {
+        let half = vehicle.length / 2.0;
+        let (left, top) = (self.to_meters(self.area_x), self.to_meters(self.area_y));
+        vehicle.x >= left - half &&
+            vehicle.x <= left + self.to_meters(self.window_width) + half &&
+            vehicle.y >= top - half &&
+            vehicle.y <= top + self.to_meters(self.window_height) + half
+    }
+}
+
+/// Circular arc followed by the axles of a turning vehicle, in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TurnPath {
+    /// Position where the turn starts.
+    pub start: (f64, f64),
+    /// Position where the turn ends, on the lane turned into.
+    pub end: (f64, f64),
+    pub radius: f64,
+    /// Heading at the start of the turn, in degrees (see `RoadDirection::heading`).
+    pub heading: f64,
+    /// 1 for a right turn (clockwise on screen), -1 for a left turn.
+    pub sign: f64,
+}
+
+impl TurnPath {
+    pub fn length(&self) -> f64 {
+        self.radius * FRAC_PI_2
+    }
+
+    /// Heading after `progress` meters along the arc, in degrees.
+    pub fn heading_at(&self, progress: f64) -> f64 {
+        self.heading + self.sign * (progress / self.radius).to_degrees()
+    }
+
+    /// Position after `progress` meters along the arc.
+    pub fn position_at(&self, progress: f64) -> (f64, f64) {
+        // The center of the arc is on the side the vehicle turns to
+        let normal = self.heading.to_radians() + self.sign * FRAC_PI_2;
+        let center_x = self.start.0 + self.radius * normal.cos();
+        let center_y = self.start.1 + self.radius * normal.sin();
+        let normal = self.heading_at(progress).to_radians() + self.sign * FRAC_PI_2;
+        (center_x - self.radius * normal.cos(), center_y - self.radius * normal.sin())
+    }
+
+    /// Position after `progress` meters along the arc, carried on straight along the lane
+    /// of the approach before the arc (negative progress) and along the lane turned into
+    /// after it.
+    pub fn point_at(&self, progress: f64) -> (f64, f64) {
+        let (origin, heading, along) = if progress < 0.0 {
+            (self.start, self.heading, progress)
+        } else if progress > self.length() {
+            (self.end, self.heading_at(self.length()), progress - self.length())
+        } else {
+            return self.position_at(progress);
+        };
+        let (sin, cos) = heading.to_radians().sin_cos();
+        (origin.0 + along * cos, origin.1 + along * sin)
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::centered(WINDOW_WIDTH, WINDOW_HEIGHT, 50, 3, DEFAULT_PIXELS_PER_METER)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ clock::Clock, config::Config, testing, vehicle_type::VehicleKind };
+
+    const ROADS: [RoadDirection; 4] = [
+        RoadDirection::North,
+        RoadDirection::West,
+        RoadDirection::South,
+        RoadDirection::East,
+    ];
+
+    /// Coordinate of (`x`, `y`) across the roads of `road_direction`.
+    fn across(road_direction: RoadDirection, (x, y): (f64, f64)) -> f64 {
+        match road_direction {
+            RoadDirection::North | RoadDirection::South => x,
+            RoadDirection::West | RoadDirection::East => y,
+        }
+    }
+
+    fn assert_near(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-6, "{} instead of {}", value, expected);
+    }
+
+    /// Difference between two headings, in degrees between -180 and 180.
+    fn turned(from: f64, to: f64) -> f64 {
+        (to - from + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    #[test]
+    fn turn_paths_join_the_lane_turned_into() {
+        let geometry = Geometry::default();
+        let intersection = geometry.intersection();
+        for road in ROADS {
+            for (direction, lane) in [(Direction::Left, 2), (Direction::Right, 0)] {
+                let line = Line::new(road, lane, vec![direction]);
+                let turn = geometry.turn_path(&line, direction, 3.0).unwrap();
+                let exit = road.after_turn(direction);
+                let lane_center = |road| geometry.to_meters(geometry.lane_center(road, lane));
+
+                assert_near(across(road, turn.start), lane_center(road));
+                assert_near(across(exit, turn.end), lane_center(exit));
+                let (x, y) = turn.position_at(turn.length());
+                assert_near(x, turn.end.0);
+                assert_near(y, turn.end.1);
+                assert_near(turned(turn.heading_at(turn.length()), exit.heading()), 0.0);
+                // Tangent to the edge of the intersection on both sides
+                let (left, top) = (intersection.x, intersection.y);
+                let (right, bottom) = (left + intersection.width, top + intersection.height);
+                for (x, y) in [turn.start, turn.end] {
+                    let on_edge = [x - left, x - right, y - top, y - bottom]
+                        .iter()
+                        .any(|offset| offset.abs() < 1e-6);
+                    assert!(on_edge, "({}, {}) is not on the edge of the intersection", x, y);
+                }
+            }
+        }
+        let straight = Line::new(RoadDirection::North, 1, vec![Direction::Straight]);
+        assert!(geometry.turn_path(&straight, Direction::Straight, 3.0).is_none());
+    }
+
+    #[test]
+    fn long_vehicles_start_turning_before_the_intersection() {
+        let geometry = Geometry::default();
+        let line = Line::new(RoadDirection::North, 0, vec![Direction::Right]);
+        let tight = geometry.turn_path(&line, Direction::Right, 3.0).unwrap();
+        let wide = geometry.turn_path(&line, Direction::Right, 10.0).unwrap();
+        assert_near(wide.radius, 10.0 * MIN_TURN_RADIUS_PER_WHEELBASE);
+        let extra = wide.radius - tight.radius;
+        // Pulled back along the approach, and pushed out along the road turned into
+        assert_near(wide.start.1, tight.start.1 - extra);
+        assert_near(wide.end.0, tight.end.0 - extra);
+        assert_near(wide.end.1, tight.end.1);
+    }
+
+    #[test]
+    fn vehicles_turn_smoothly_into_their_lane() {
+        let config = Config::default();
+        let geometry = config.geometry;
+        let clock = Clock::new(config.tick_duration);
+        for road in ROADS {
+            for direction in [Direction::Left, Direction::Right] {
+                for kind in [VehicleKind::Car, VehicleKind::Bus] {
+                    let mut vehicle = testing::spawn(0, &config, kind, road, direction);
+                    let lane = Line::approach(road)
+                        .into_iter()
+                        .find(|line| line.directions.contains(&direction))
+                        .unwrap()
+                        .lane;
+                    let exit = road.after_turn(direction);
+                    let turn = vehicle.turn.unwrap();
+                    // Never against the turn, nor faster than along the arc
+                    let distance = vehicle.velocity * clock.dt.as_secs_f64();
+                    let max = (distance / turn.radius).to_degrees();
+                    while vehicle.turn.is_some() {
+                        let heading = vehicle.heading;
+                        vehicle.step(clock.dt);
+                        let step = turned(heading, vehicle.heading) * turn.sign;
+                        assert!(geometry.is_on_screen(&vehicle), "{:?} {:?} left", road, direction);
+                        assert!(
+                            (-1e-9..=max + 1e-9).contains(&step),
+                            "{:?} {:?} turned by {}°",
+                            road,
+                            direction,
+                            step
+                        );
+                    }
+                    assert_eq!(vehicle.road_direction, exit);
+                    assert_near(vehicle.heading, exit.heading());
+                    let lane_center = geometry.to_meters(geometry.lane_center(exit, lane));
+                    assert_near(across(exit, (vehicle.x, vehicle.y)), lane_center);
+                }
+            }
+        }
+    }}
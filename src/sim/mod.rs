@@ -1,3 +1,23 @@
+pub mod car_following;
+pub mod clock;
+pub mod collision;
+pub mod config;
+pub mod emergency;
+pub mod geometry;
+pub mod network;
+pub mod pedestrians;
+pub mod policy;
+pub mod recording;
+pub mod reservation;
+pub mod routing;
 pub mod roads;
+pub mod safety;
+pub mod scenario;
+pub mod stats;
+#[cfg(test)]
+mod testing;
+pub mod trips;
+pub mod units;
 pub mod vehicle;
+pub mod vehicle_type;
 pub mod vehicles_management;
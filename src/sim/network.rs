@@ -0,0 +1,691 @@
+use std::{ collections::HashMap, path::Path };
+use rand::{ rngs::StdRng, Rng, SeedableRng };
+
+use super::{
+    config::Config,
+    policy::{ self, SignalTimings },
+    roads::{ Direction, Line, RoadDirection },
+    routing::{ Node, RoadGraph, Route },
+    scenario::Scenario,
+    vehicle::Vehicle,
+    vehicle_type::VehicleKind,
+    vehicles_management::VehiclesManagement,
+};
+
+/// Size of a grid of intersections and the policy of each of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkLayout {
+    pub rows: usize,
+    pub columns: usize,
+    /// Names of the policies of the intersections, row by row; the intersections past the
+    /// end of the list use the policy chosen at startup.
+    pub policies: Vec<String>,
+}
+
+impl Default for NetworkLayout {
+    fn default() -> Self {
+        NetworkLayout { rows: 1, columns: 1, policies: vec![] }
+    }
+}
+
+impl NetworkLayout {
+    /// Row and column of the intersection at `index`.
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index / self.columns, index % self.columns)
+    }
+
+    /// Intersection reached by the vehicles leaving the one at `index` on the road of
+    /// `road`, `None` at the edge of the grid.
+    pub fn next(&self, index: usize, road: RoadDirection) -> Option<usize> {
+        let (row, column) = self.position(index);
+        let (row, column) = match road {
+            RoadDirection::North => (row + 1, column),
+            RoadDirection::South => (row.checked_sub(1)?, column),
+            RoadDirection::West => (row, column.checked_sub(1)?),
+            RoadDirection::East => (row, column + 1),
+        };
+        if row >= self.rows || column >= self.columns {
+            return None;
+        }
+        Some(row * self.columns + column)
+    }
+}
+
+/// Grid of intersections, each simulated by its own `VehiclesManagement` with its own
+/// policy instance, in an area the size of the window next to the areas of its neighbours.
+///
+/// The road a vehicle leaves an intersection by is an approach of the next one, which takes
+/// the vehicle over as it leaves the area of the first, so vehicles only spawn on the
+/// approaches at the edge of the grid. Each vehicle is spawned with a random destination
+/// among the exits of the grid it can reach and takes the manoeuvres of its shortest route
+/// there, changing lane on its way to an intersection if its manoeuvre there isn't
+/// permitted from its lane: it moves across one lane at a time, at `LANE_CHANGE_VELOCITY`,
+/// once the lane next to it has a gap for it. A vehicle that can't be in a lane of its
+/// manoeuvre before the stop line is routed again from the lane it is in, and counted in
+/// `missed` if its destination is out of reach from there. The vehicles of a scenario take
+/// the manoeuvre of the event at their first intersection and get a random destination as
+/// they leave it. A vehicle leaving an intersection keeps its distance to the vehicles that
+/// already entered the next one. A single intersection is a 1 × 1 grid.
+#[derive(Debug)]
+pub struct Network {
+    pub layout: NetworkLayout,
+    /// Intersections row by row, the top-left one first.
+    pub intersections: Vec<VehiclesManagement>,
+    /// Lines of every approach of every intersection, one `Vec` per approach.
+    pub approaches: Vec<Vec<Line>>,
+    pub graph: RoadGraph,
+    /// Route on from its current intersection of every vehicle in the grid, by intersection
+    /// index and vehicle id.
+    pub routes: HashMap<(usize, i32), Route>,
+    /// Vehicles that left the grid at their destination.
+    pub arrived: usize,
+    /// Vehicles that couldn't reach their destination any more.
+    pub missed: usize,
+    pub seed: u64,
+    /// Picks the intersection, destination and lane of random spawns and the lane changes,
+    /// the intersections drawing everything else from their own RNG.
+    rng: StdRng,
+}
+
+impl Network {
+    /// Grid described by `config`. The first intersection draws its random traffic from
+    /// `seed` like a single `VehiclesManagement` would, the next ones from the following seeds.
+    pub fn with_config(seed: u64, config: &Config) -> Self {
+        let layout = config.network.clone();
+        let mut intersections = vec![];
+        for row in 0..layout.rows {
+            for column in 0..layout.columns {
+                let config = Config { geometry: config.geometry.at(row, column), ..config.clone() };
+                let index = (row * layout.columns + column) as u64;
+                intersections.push(VehiclesManagement::with_config(seed.wrapping_add(index), &config));
+            }
+        }
+        let rng = StdRng::seed_from_u64(seed.wrapping_add(intersections.len() as u64));
+        let graph = RoadGraph::new(&layout, &config.approaches, &config.geometry);
+        Network {
+            layout,
+            intersections,
+            approaches: config.approaches.clone(),
+            graph,
+            routes: HashMap::new(),
+            arrived: 0,
+            missed: 0,
+            seed,
+            rng,
+        }
+    }
+
+    /// Install on every intersection the policy the layout names for it, or `default` for
+    /// those it doesn't (the `ReservationPolicy` when `None`).
+    pub fn set_policies(
+        &mut self,
+        default: Option<&str>,
+        signal_timings: SignalTimings
+    ) -> Result<(), String> {
+        for (i, intersection) in self.intersections.iter_mut().enumerate() {
+            let name = self.layout.policies.get(i).map(String::as_str).or(default);
+            if let Some(name) = name {
+                intersection.set_policy(
+                    policy::by_name(name, &intersection.geometry, signal_timings)?
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Row and column of the intersection at `index`.
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        self.layout.position(index)
+    }
+
+    /// Width and height of the whole grid, in pixels.
+    pub fn size(&self) -> (i32, i32) {
+        let geometry = &self.intersections[0].geometry;
+        (
+            geometry.window_width * (self.layout.columns as i32),
+            geometry.window_height * (self.layout.rows as i32),
+        )
+    }
+
+    /// Whether the vehicles of the `road` approach of the intersection at `index` come from
+    /// outside the grid.
+    pub fn is_entry(&self, index: usize, road: RoadDirection) -> bool {
+        self.next(index, road.opposite()).is_none()
+    }
+
+    /// Intersection reached by the vehicles leaving the one at `index` on the road of
+    /// `road`, `None` at the edge of the grid.
+    pub fn next(&self, index: usize, road: RoadDirection) -> Option<usize> {
+        self.layout.next(index, road)
+    }
+
+    /// Lines of the `road` approach.
+    fn approach(&self, road: RoadDirection) -> Option<&Vec<Line>> {
+        self.approaches.iter().find(|lines| lines[0].road_direction == road)
+    }
+
+    /// Approaches of the intersection at `index` whose vehicles come from outside the grid.
+    pub fn entries(&self, index: usize) -> Vec<&Vec<Line>> {
+        self.approaches
+            .iter()
+            .filter(|lines| self.is_entry(index, lines[0].road_direction))
+            .collect()
+    }
+
+    /// Index of a random intersection among those matching `filter`.
+    fn random_intersection(&mut self, filter: impl Fn(&Network, usize) -> bool) -> Option<usize> {
+        let candidates: Vec<usize> = (0..self.intersections.len())
+            .filter(|&index| filter(self, index))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[self.rng.gen_range(0..candidates.len())])
+    }
+
+    /// Spawn a vehicle on the `road` approach of a random intersection at the edge of the grid.
+    pub fn spawn(&mut self, road: RoadDirection) {
+        if self.approach(road).is_none() {
+            return;
+        }
+        if let Some(index) = self.random_intersection(|network, index| network.is_entry(index, road)) {
+            self.spawn_routed(Node::Entry(index, road), None);
+        }
+    }
+
+    /// Spawn a vehicle on a random approach at the edge of the grid.
+    pub fn spawn_random(&mut self) {
+        if let Some(origin) = self.random_entry() {
+            self.spawn_routed(origin, None);
+        }
+    }
+
+    /// Spawn an emergency vehicle on a random approach at the edge of the grid.
+    pub fn spawn_emergency(&mut self) {
+        if let Some(origin) = self.random_entry() {
+            self.spawn_routed(origin, Some(VehicleKind::Emergency));
+        }
+    }
+
+    /// Random approach at the edge of the grid.
+    fn random_entry(&mut self) -> Option<Node> {
+        let index = self.random_intersection(|network, index| !network.entries(index).is_empty())?;
+        let roads: Vec<RoadDirection> = self.entries(index)
+            .into_iter()
+            .map(|lines| lines[0].road_direction)
+            .collect();
+        let road = roads[self.rng.gen_range(0..roads.len())];
+        Some(Node::Entry(index, road))
+    }
+
+    /// Spawn a vehicle of `kind` (drawn from the traffic mix when `None`) at `origin`, with
+    /// the route to a random exit it can reach, on a random lane of the first manoeuvre.
+    fn spawn_routed(&mut self, origin: Node, kind: Option<VehicleKind>) {
+        let Node::Entry(index, road) = origin else {
+            return;
+        };
+        let routes: Vec<Route> = self.graph
+            .exits()
+            .into_iter()
+            .filter_map(|exit| self.graph.route(origin, exit))
+            .collect();
+        if routes.is_empty() {
+            return;
+        }
+        let route = routes[self.rng.gen_range(0..routes.len())].clone();
+        let direction = route.steps[0].direction;
+        let lines: Vec<Line> = self.approach(road)
+            .into_iter()
+            .flatten()
+            .filter(|line| line.directions.contains(&direction))
+            .cloned()
+            .collect();
+        let line = &lines[self.rng.gen_range(0..lines.len())];
+        let intersection = &mut self.intersections[index];
+        let kind = kind.unwrap_or_else(|| intersection.random_kind());
+        if let Some(id) = intersection.spawn_on(line, direction, kind) {
+            self.routes.insert((index, id), route);
+        }
+    }
+
+    /// Route to a random exit reachable from the `road` approach of the intersection at
+    /// `index`.
+    fn random_route(&mut self, index: usize, road: RoadDirection) -> Option<Route> {
+        let lanes: Vec<i32> = self.approach(road)
+            .into_iter()
+            .flatten()
+            .map(|line| line.lane)
+            .collect();
+        let routes: Vec<Route> = self.graph
+            .exits()
+            .into_iter()
+            .filter_map(|exit| self.graph.route_on(index, road, &lanes, exit))
+            .collect();
+        if routes.is_empty() {
+            return None;
+        }
+        Some(routes[self.rng.gen_range(0..routes.len())].clone())
+    }
+
+    /// Spawn a pedestrian at a random intersection.
+    pub fn spawn_pedestrian(&mut self) {
+        if let Some(index) = self.random_intersection(|_, _| true) {
+            self.intersections[index].spawn_pedestrian();
+        }
+    }
+
+    /// Play back `scenario`, each spawn on its approach at the first intersection (row by
+    /// row) where that approach enters the grid.
+    pub fn play(&mut self, scenario: &Scenario) {
+        for index in 0..self.intersections.len() {
+            let events = scenario.events
+                .iter()
+                .filter(|event| {
+                    let road = event.line.road_direction;
+                    (0..self.intersections.len()).find(|&first| self.is_entry(first, road)) ==
+                        Some(index)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !events.is_empty() {
+                self.intersections[index].play(&Scenario { events, ..scenario.clone() });
+            }
+        }
+    }
+
+    /// Whether scenario spawns are still to come.
+    pub fn is_playing(&self) -> bool {
+        self.intersections.iter().any(VehiclesManagement::is_playing)
+    }
+
+    /// Record the session of a single intersection to `path` (see `VehiclesManagement::record`).
+    pub fn record(&mut self, path: &Path) -> Result<(), String> {
+        if self.intersections.len() > 1 {
+            return Err("only a single intersection can be recorded".to_string());
+        }
+        let approaches = self.approaches.clone();
+        self.intersections[0].record(path, &approaches)
+    }
+
+    /// Stop recording, reporting any error met while writing the file.
+    pub fn finish_recording(&mut self) -> Result<(), String> {
+        self.intersections[0].finish_recording()
+    }
+
+    /// Intersection halted on a crash, if any.
+    pub fn halted(&self) -> Option<&VehiclesManagement> {
+        self.intersections.iter().find(|intersection| intersection.collisions.is_halted())
+    }
+
+    /// Advance every intersection by one tick, unless one of them halted on a crash, then
+    /// hand the vehicles that left an intersection over to the next one and move the vehicles
+    /// on their way to the next one to the lanes of their routes.
+    pub fn update(&mut self) {
+        if self.halted().is_some() {
+            return;
+        }
+        for index in 0..self.intersections.len() {
+            let neighbours: Vec<Vehicle> = [
+                RoadDirection::North,
+                RoadDirection::West,
+                RoadDirection::South,
+                RoadDirection::East,
+            ]
+                .into_iter()
+                .filter_map(|road| self.next(index, road))
+                .flat_map(|neighbour| self.intersections[neighbour].list.iter())
+                .map(|vehicle| Vehicle { id: -1, ..vehicle.clone() })
+                .collect();
+            self.intersections[index].neighbours = neighbours;
+        }
+
+        for intersection in &mut self.intersections {
+            intersection.update();
+        }
+
+        for index in 0..self.intersections.len() {
+            let exited = self.intersections[index].exited.clone();
+            for vehicle in exited {
+                let route = self.routes.remove(&(index, vehicle.id));
+                let Some(next) = self.next(index, vehicle.road_direction) else {
+                    if let Some(route) = route {
+                        if route.destination == Node::Exit(index, vehicle.road_direction) {
+                            self.arrived += 1;
+                        } else {
+                            self.missed += 1;
+                        }
+                    }
+                    continue;
+                };
+                self.hand_over(index, next, &vehicle, route);
+            }
+        }
+        for index in 0..self.intersections.len() {
+            self.change_lanes(index);
+        }
+    }
+
+    /// Start the lane changes of the vehicles of the intersection at `index` that aren't in a
+    /// lane of the manoeuvre of their route at the intersection ahead: on the approach of
+    /// `index`, or on their way to the next intersection once they left `index`. A vehicle
+    /// moves over one lane at a time, once the lane next to it has the room for it. On its
+    /// approach, a vehicle that can't be through before the stop line any more is routed
+    /// again from the lane it is in. On its way to the next intersection, it waits for the
+    /// vehicles still crossing `index` into the lane it moves to or close behind it, and slows
+    /// down while it has no room.
+    fn change_lanes(&mut self, index: usize) {
+        self.intersections[index].yielding.clear();
+        for position in 0..self.intersections[index].list.len() {
+            let intersection = &self.intersections[index];
+            let vehicle = intersection.list[position].clone();
+            if vehicle.lane_change.is_some() {
+                continue;
+            }
+            let road = vehicle.road_direction;
+            let route = self.routes.get(&(index, vehicle.id));
+            let has_left = intersection.trips[vehicle.id as usize].exit_tick.is_some() &&
+                !intersection.intersection.overlaps(&vehicle);
+            let step = if vehicle.distance_to_intersection(&intersection.stop_area).is_some() {
+                route.and_then(|route| route.steps.first()).filter(|step| step.intersection == index)
+            } else if has_left {
+                route
+                    .and_then(|route| route.steps.get(1))
+                    .filter(|step| Some(step.intersection) == self.next(index, road))
+            } else {
+                None
+            };
+            let Some(step) = step.filter(|step| step.road_direction == road).copied() else {
+                continue;
+            };
+            let lane = self.lane_of(index, &vehicle);
+            let Some(target) = self.line_for(index, &vehicle, step.direction) else {
+                continue;
+            };
+            if target.lane == lane {
+                continue;
+            }
+            let stop_area = &self.intersections[step.intersection].stop_area;
+            if !vehicle.can_change_lane(self.lane_center(index, road, target.lane), stop_area) {
+                if step.intersection == index {
+                    let route = self.routes.remove(&(index, vehicle.id));
+                    let (line, direction, route) = self.reroute(index, road, lane, route);
+                    self.intersections[index].change_manoeuvre(vehicle.id, &line, direction);
+                    if let Some(route) = route {
+                        self.routes.insert((index, vehicle.id), route);
+                    }
+                }
+                continue;
+            }
+            let next_lane = lane + (target.lane - lane).signum();
+            if has_left && self.is_crossing_into(index, &vehicle, lane, next_lane) {
+                continue;
+            }
+            if self.has_room(index, &self.in_lane(index, &vehicle, next_lane)) {
+                let center = self.lane_center(index, road, next_lane);
+                self.intersections[index].list[position].lane_change = Some(center);
+            } else if has_left {
+                self.intersections[index].yielding.push(vehicle.id);
+            }
+        }
+    }
+
+    /// Whether a vehicle in the box of the intersection at `index`, which couldn't brake there
+    /// for `vehicle` changing lane from `lane` to `next_lane` of the road it left it by, is
+    /// on its way behind it: to `next_lane`, where it was let in for the room it had, or to
+    /// `lane` close enough to have to brake.
+    fn is_crossing_into(
+        &self,
+        index: usize,
+        vehicle: &Vehicle,
+        lane: i32,
+        next_lane: i32
+    ) -> bool {
+        let intersection = &self.intersections[index];
+        let road = vehicle.road_direction;
+        intersection.list.iter().any(|other| {
+            if
+                !intersection.intersection.overlaps(other) ||
+                other.road_direction.after_turn(other.direction) != road
+            {
+                return false;
+            }
+            let (x, y) = other.turn.map_or((other.x, other.y), |turn| turn.end);
+            let across = match road {
+                RoadDirection::North | RoadDirection::South => x,
+                RoadDirection::West | RoadDirection::East => y,
+            };
+            let gap =
+                (vehicle.x - other.x).hypot(vehicle.y - other.y) -
+                (vehicle.length + other.length) / 2.0;
+            let other_lane = intersection.geometry.lane_at(road, across);
+            other_lane == next_lane ||
+                (other_lane == lane &&
+                    gap <
+                        other.profile.safe_distance * 2.0 +
+                            other.profile.braking_distance(other.velocity))
+        })
+    }
+
+    /// Line of the approach of `vehicle`, on a road of the intersection at `index`,
+    /// permitting `direction` and nearest to its lane.
+    fn line_for(&self, index: usize, vehicle: &Vehicle, direction: Direction) -> Option<Line> {
+        let lane = self.lane_of(index, vehicle);
+        self.approach(vehicle.road_direction)?
+            .iter()
+            .filter(|line| line.directions.contains(&direction))
+            .min_by_key(|line| (line.lane - lane).abs())
+            .cloned()
+    }
+
+    /// Whether `vehicle`, on a road of the intersection at `index`, has room where it is
+    /// among the vehicles of every intersection.
+    fn has_room(&self, index: usize, vehicle: &Vehicle) -> bool {
+        self.intersections.iter().enumerate().all(|(other, intersection)| {
+            if other == index {
+                intersection.has_room(vehicle)
+            } else {
+                intersection.has_room(&Vehicle { id: -1, ..vehicle.clone() })
+            }
+        })
+    }
+
+    /// Lane `vehicle`, on a road of the intersection at `index`, is driving in, or moving to
+    /// while it changes lane.
+    fn lane_of(&self, index: usize, vehicle: &Vehicle) -> i32 {
+        let geometry = &self.intersections[index].geometry;
+        let across = vehicle.lane_change.unwrap_or(vehicle.across());
+        geometry.lane_at(vehicle.road_direction, across)
+    }
+
+    /// Position across the road of the center of `lane` of the `road` approach, on a road of
+    /// the intersection at `index`.
+    fn lane_center(&self, index: usize, road: RoadDirection, lane: i32) -> f64 {
+        let geometry = &self.intersections[index].geometry;
+        geometry.to_meters(geometry.lane_center(road, lane))
+    }
+
+    /// `vehicle`, on a road of the intersection at `index`, moved across its road to the
+    /// center of `lane`.
+    fn in_lane(&self, index: usize, vehicle: &Vehicle, lane: i32) -> Vehicle {
+        vehicle.moved_across(self.lane_center(index, vehicle.road_direction, lane))
+    }
+
+    /// Line of `lane` of the `road` approach of the intersection at `index` and the
+    /// manoeuvre a vehicle with `route` in that lane takes there: the first of its route from
+    /// the lane to its destination, or a random one of the lane if it can't reach its
+    /// destination any more.
+    fn reroute(
+        &mut self,
+        index: usize,
+        road: RoadDirection,
+        lane: i32,
+        route: Option<Route>
+    ) -> (Line, Direction, Option<Route>) {
+        // A lane the configuration gives no manoeuvre to goes straight on
+        let current = self
+            .approach(road)
+            .and_then(|lines| lines.iter().find(|line| line.lane == lane))
+            .cloned()
+            .unwrap_or_else(|| Line::new(road, lane, vec![Direction::Straight]));
+        let rerouted = route.as_ref().and_then(|route| {
+            let rerouted = self.graph.route_on(index, road, &[lane], route.destination)?;
+            Some(Route { origin: route.origin, ..rerouted })
+        });
+        match rerouted {
+            Some(route) => (current, route.steps[0].direction, Some(route)),
+            None => {
+                if route.is_some() {
+                    self.missed += 1;
+                }
+                let direction = if current.directions.is_empty() {
+                    Direction::Straight
+                } else {
+                    current.directions[self.rng.gen_range(0..current.directions.len())]
+                };
+                (current, direction, None)
+            }
+        }
+    }
+
+    /// Hand `vehicle`, which left the intersection at `index` with `route`, over to the one
+    /// at `next`, with its next manoeuvre if it is in a lane of it or can still change lane
+    /// to one, routed again from the lane it is in otherwise.
+    fn hand_over(&mut self, index: usize, next: usize, vehicle: &Vehicle, route: Option<Route>) {
+        let road = vehicle.road_direction;
+        // The vehicles spawned without a route (by a scenario) get one here
+        let route = match route {
+            Some(mut route) => {
+                route.steps.remove(0);
+                Some(route)
+            }
+            None => self.random_route(next, road),
+        };
+        let step = route
+            .as_ref()
+            .and_then(|route| route.steps.first())
+            .filter(|step| step.intersection == next && step.road_direction == road)
+            .copied();
+        // The line of its manoeuvre, if the vehicle is in it or can still change lane to it
+        let target = step.and_then(|step| {
+            let line = self.line_for(index, vehicle, step.direction)?;
+            let center = self.lane_center(index, road, line.lane);
+            let stop_area = &self.intersections[next].stop_area;
+            let is_reachable = line.lane == self.lane_of(index, vehicle) ||
+                vehicle.can_change_lane(center, stop_area);
+            is_reachable.then_some((line, step.direction))
+        });
+        let (line, direction, route) = match target {
+            Some((line, direction)) => (line, direction, route),
+            // Routed on from the lane the vehicle is in
+            None => {
+                let lane = self.lane_of(index, vehicle);
+                self.reroute(next, road, lane, route)
+            }
+        };
+        let id = self.intersections[next].enter(vehicle, &line, direction);
+        if let Some(route) = route {
+            self.routes.insert((next, id), route);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::vehicle::LANE_CHANGE_VELOCITY;
+
+    #[test]
+    fn vehicle_is_handed_over_to_the_next_intersection() {
+        let mut config = Config::default();
+        config.network.columns = 2;
+        let mut network = Network::with_config(0, &config);
+        // A car going straight through both intersections, from the right edge of the grid
+        let origin = Node::Entry(1, RoadDirection::West);
+        let route = network.graph.route(origin, Node::Exit(0, RoadDirection::West)).unwrap();
+        let line = network
+            .approach(RoadDirection::West)
+            .and_then(|lines| {
+                lines.iter().find(|line| line.directions.contains(&Direction::Straight))
+            })
+            .cloned()
+            .unwrap();
+        let id = network.intersections[1]
+            .spawn_on(&line, Direction::Straight, VehicleKind::Car)
+            .unwrap();
+        network.routes.insert((1, id), route);
+
+        let mut last: Option<Vehicle> = None;
+        for _ in 0..10_000 {
+            network.update();
+            let vehicles: Vec<&Vehicle> = network.intersections
+                .iter()
+                .flat_map(|intersection| intersection.list.iter())
+                .collect();
+            assert!(vehicles.len() <= 1, "the car is in two intersections at once");
+            let Some(&vehicle) = vehicles.first() else {
+                break;
+            };
+            // It drives on at its velocity from one intersection to the next
+            if let Some(last) = &last {
+                let moved = (vehicle.x - last.x).hypot(vehicle.y - last.y);
+                let dt = network.intersections[0].clock.dt.as_secs_f64();
+                assert!(moved <= vehicle.velocity.max(last.velocity) * dt + 1e-9);
+                assert_eq!(vehicle.y, last.y);
+            }
+            last = Some(vehicle.clone());
+        }
+        let trips = &network.intersections[0].trips;
+        assert_eq!(trips.len(), 1);
+        assert_eq!(trips[0].origin, RoadDirection::West);
+        assert!(trips[0].despawn_tick.is_some());
+        assert_eq!((network.arrived, network.missed), (1, 0));
+    }
+
+    #[test]
+    fn vehicle_changes_lane_for_its_next_manoeuvre() {
+        let mut config = Config::default();
+        config.network.columns = 2;
+        let mut network = Network::with_config(0, &config);
+        // A car from the right edge of the grid going straight on in the middle lane, then
+        // turning left at the next intersection, from the left lane
+        let origin = Node::Entry(1, RoadDirection::West);
+        let exit = RoadDirection::West.after_turn(Direction::Left);
+        let route = network.graph.route(origin, Node::Exit(0, exit)).unwrap();
+        let line = network
+            .approach(RoadDirection::West)
+            .and_then(|lines| {
+                lines.iter().find(|line| line.directions == [Direction::Straight])
+            })
+            .cloned()
+            .unwrap();
+        let id = network.intersections[1]
+            .spawn_on(&line, Direction::Straight, VehicleKind::Car)
+            .unwrap();
+        network.routes.insert((1, id), route);
+
+        let dt = network.intersections[0].clock.dt.as_secs_f64();
+        let mut changes = 0;
+        let mut last: Option<Vehicle> = None;
+        for _ in 0..10_000 {
+            network.update();
+            let Some(vehicle) = network.intersections
+                .iter()
+                .flat_map(|intersection| intersection.list.iter())
+                .next()
+                .cloned() else {
+                break;
+            };
+            // It moves over to the next lane at the lane change velocity
+            if let (Some(last), Some(_)) = (&last, vehicle.lane_change) {
+                assert!((vehicle.y - last.y).abs() <= LANE_CHANGE_VELOCITY * dt + 1e-9);
+                if last.lane_change.is_none() {
+                    changes += 1;
+                }
+            }
+            last = Some(vehicle);
+        }
+        assert_eq!(changes, 1);
+        assert_eq!(network.intersections[0].trips[0].direction, Direction::Left);
+        assert_eq!((network.arrived, network.missed), (1, 0));
+    }
+}
@@ -0,0 +1,331 @@
+use std::{ collections::HashSet, time::Duration };
+use serde::{ Deserialize, Serialize };
+
+use super::{
+    clock::Clock,
+    geometry::Geometry,
+    roads::{ Crosswalk, RoadDirection, RoadIntersection },
+    trips::TimeSummary,
+    vehicle::Vehicle,
+};
+
+/// Meters per second.
+pub const WALKING_SPEED: f64 = 1.4;
+/// Distance from the road edge to where pedestrians wait on the sidewalk, in meters.
+const CURB_DISTANCE: f64 = 1.0;
+/// Distance between a vehicle and a pedestrian under which they are in conflict, in meters.
+pub const CONFLICT_DISTANCE: f64 = 1.0;
+
+/// Pedestrian spawns and walking speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PedestrianTraffic {
+    /// Simulated time between two pedestrians spawned at random, `None` for none.
+    pub interval: Option<Duration>,
+    /// Meters per second.
+    pub walking_speed: f64,
+}
+
+impl Default for PedestrianTraffic {
+    fn default() -> Self {
+        PedestrianTraffic { interval: None, walking_speed: WALKING_SPEED }
+    }
+}
+
+/// Pedestrian crossing the road of an approach on its crosswalk, from one sidewalk to the
+/// other. Positions are in meters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pedestrian {
+    pub id: i32,
+    /// Approach whose crosswalk the pedestrian walks over.
+    pub road: RoadDirection,
+    pub x: f64,
+    pub y: f64,
+    /// Where the pedestrian walks to, on the far sidewalk.
+    pub to: (f64, f64),
+    pub spawn_tick: u64,
+    /// Tick the pedestrian stepped off the sidewalk, `None` while it waits to cross.
+    pub start_tick: Option<u64>,
+}
+
+impl Pedestrian {
+    pub fn is_crossing(&self) -> bool {
+        self.start_tick.is_some()
+    }
+}
+
+/// Pedestrians, the crosswalks they use, and their statistics.
+///
+/// A pedestrian arriving at a crosswalk requests to cross: vehicles with the crosswalk
+/// ahead that can still stop comfortably at their stop line wait there, and the pedestrian
+/// steps onto the crosswalk once no vehicle is committed to cross it anymore. Vehicles then
+/// wait for the crosswalk to be clear. The stop lines being at the edge of the stop area
+/// (see `Geometry::stop_area`), vehicles waiting for their right of way keep clear of the
+/// crosswalks.
+#[derive(Debug)]
+pub struct Pedestrians {
+    pub list: Vec<Pedestrian>,
+    /// Crosswalks over the roads of the four approaches.
+    pub crosswalks: [Crosswalk; 4],
+    pub traffic: PedestrianTraffic,
+    pub number_of_pedestrians: i32,
+    last_spawn_tick: Option<u64>,
+    /// Ticks every pedestrian that started crossing waited on the sidewalk.
+    pub waits: Vec<u64>,
+    /// Pedestrians that reached the far sidewalk.
+    pub crossed: usize,
+    /// Pairs (pedestrian, vehicle) that came closer than `CONFLICT_DISTANCE`.
+    pub conflicts: HashSet<(i32, i32)>,
+}
+
+impl Pedestrians {
+    pub fn new(geometry: &Geometry, traffic: PedestrianTraffic) -> Self {
+        Pedestrians {
+            list: vec![],
+            crosswalks: [
+                RoadDirection::North,
+                RoadDirection::West,
+                RoadDirection::South,
+                RoadDirection::East,
+            ].map(|road| geometry.crosswalk(road)),
+            traffic,
+            number_of_pedestrians: 0,
+            last_spawn_tick: None,
+            waits: vec![],
+            crossed: 0,
+            conflicts: HashSet::new(),
+        }
+    }
+
+    fn crosswalk(&self, road: RoadDirection) -> &Crosswalk {
+        self.crosswalks
+            .iter()
+            .find(|crosswalk| crosswalk.road == road)
+            .expect("every road has a crosswalk")
+    }
+
+    /// Add a pedestrian on the sidewalk at one end of the crosswalk of `road`, at the
+    /// near end on screen (left or top) when `from_start`.
+    pub fn spawn(&mut self, road: RoadDirection, from_start: bool, clock: &Clock) {
+        let crosswalk = *self.crosswalk(road);
+        let (start, end) = match road {
+            RoadDirection::North | RoadDirection::South => {
+                let y = crosswalk.y + crosswalk.height / 2.0;
+                (
+                    (crosswalk.x - CURB_DISTANCE, y),
+                    (crosswalk.x + crosswalk.width + CURB_DISTANCE, y),
+                )
+            }
+            RoadDirection::West | RoadDirection::East => {
+                let x = crosswalk.x + crosswalk.width / 2.0;
+                (
+                    (x, crosswalk.y - CURB_DISTANCE),
+                    (x, crosswalk.y + crosswalk.height + CURB_DISTANCE),
+                )
+            }
+        };
+        let (from, to) = if from_start { (start, end) } else { (end, start) };
+        self.list.push(Pedestrian {
+            id: self.number_of_pedestrians,
+            road,
+            x: from.0,
+            y: from.1,
+            to,
+            spawn_tick: clock.tick,
+            start_tick: None,
+        });
+        self.number_of_pedestrians += 1;
+        self.last_spawn_tick = Some(clock.tick);
+    }
+
+    /// Whether the spawn interval elapsed since the last pedestrian.
+    pub fn is_due(&self, clock: &Clock) -> bool {
+        let Some(interval) = self.traffic.interval else {
+            return false;
+        };
+        self.last_spawn_tick.is_none_or(|tick| clock.elapsed(tick) >= interval)
+    }
+
+    /// Let the waiting pedestrians onto their crosswalk once the vehicles committed to it
+    /// are gone, and walk the crossing ones for a tick.
+    pub fn update(&mut self, vehicles: &[Vehicle], stop_area: &RoadIntersection, clock: &Clock) {
+        let crosswalks = self.crosswalks;
+        for pedestrian in self.list.iter_mut().filter(|pedestrian| !pedestrian.is_crossing()) {
+            let crosswalk = crosswalks
+                .iter()
+                .find(|crosswalk| crosswalk.road == pedestrian.road)
+                .expect("every road has a crosswalk");
+            let is_clear = vehicles
+                .iter()
+                .all(|vehicle| !is_committed(vehicle, crosswalk, stop_area));
+            if is_clear {
+                pedestrian.start_tick = Some(clock.tick);
+                self.waits.push(clock.tick - pedestrian.spawn_tick);
+            }
+        }
+
+        let step = self.traffic.walking_speed * clock.dt.as_secs_f64();
+        let before = self.list.len();
+        self.list.retain_mut(|pedestrian| {
+            if !pedestrian.is_crossing() {
+                return true;
+            }
+            let (dx, dy) = (pedestrian.to.0 - pedestrian.x, pedestrian.to.1 - pedestrian.y);
+            let left = (dx * dx + dy * dy).sqrt();
+            if left <= step {
+                return false;
+            }
+            pedestrian.x += (dx / left) * step;
+            pedestrian.y += (dy / left) * step;
+            true
+        });
+        self.crossed += before - self.list.len();
+    }
+
+    /// Cap the `velocities` of the vehicles that must wait for a pedestrian at the edge of
+    /// `stop_area`, for a tick of `dt`. Returns the ids of the vehicles held.
+    pub fn yield_to_pedestrians(
+        &self,
+        vehicles: &[Vehicle],
+        velocities: &mut [f64],
+        stop_area: &RoadIntersection,
+        dt: Duration
+    ) -> Vec<i32> {
+        let mut held = vec![];
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            if vehicle.distance_to_intersection(stop_area).is_none() {
+                continue;
+            }
+            let ahead: Vec<&Crosswalk> = self.crosswalks
+                .iter()
+                .filter(|crosswalk| crosswalk.is_ahead_of(vehicle, stop_area))
+                .collect();
+            // A vehicle keeping a pedestrian waiting only stops for the crossing ones, so
+            // that they never wait for each other
+            let is_blocking = ahead.iter().any(|crosswalk| {
+                is_committed(vehicle, crosswalk, stop_area) &&
+                    self.list
+                        .iter()
+                        .any(|pedestrian| pedestrian.road == crosswalk.road && !pedestrian.is_crossing())
+            });
+            let must_wait = ahead.iter().any(|crosswalk| {
+                self.list
+                    .iter()
+                    .filter(|pedestrian| pedestrian.road == crosswalk.road)
+                    .any(|pedestrian| pedestrian.is_crossing() || !is_blocking)
+            });
+            if must_wait {
+                velocities[i] = velocities[i].min(vehicle.velocity_to_stop_at(stop_area, dt));
+                held.push(vehicle.id);
+            }
+        }
+        held
+    }
+
+    /// Record the vehicles too close to a pedestrian on a crosswalk.
+    pub fn detect_conflicts(&mut self, vehicles: &[Vehicle]) {
+        for pedestrian in self.list.iter().filter(|pedestrian| pedestrian.is_crossing()) {
+            for vehicle in vehicles {
+                if vehicle.distance_to_point(pedestrian.x, pedestrian.y) < CONFLICT_DISTANCE {
+                    self.conflicts.insert((pedestrian.id, vehicle.id));
+                }
+            }
+        }
+    }
+
+    pub fn report(&self, clock: &Clock) -> PedestrianReport {
+        PedestrianReport {
+            spawned: self.number_of_pedestrians,
+            crossed: self.crossed,
+            wait_time: TimeSummary::new(self.waits.iter().map(|&ticks| clock.duration(ticks))),
+            conflicts: self.conflicts.len(),
+        }
+    }
+}
+
+/// Whether `vehicle` will cross `crosswalk` whatever happens: it is on it, in the stop area
+/// with the crosswalk ahead, or too close to stop comfortably at its stop line.
+fn is_committed(vehicle: &Vehicle, crosswalk: &Crosswalk, stop_area: &RoadIntersection) -> bool {
+    if crosswalk.overlaps(vehicle) {
+        return true;
+    }
+    if !crosswalk.is_ahead_of(vehicle, stop_area) {
+        return false;
+    }
+    match vehicle.distance_to_intersection(stop_area) {
+        Some(distance) =>
+            distance < vehicle.stopping_distance(vehicle.profile.comfortable_deceleration),
+        None => true,
+    }
+}
+
+/// Pedestrian statistics of a run. Wait times are in seconds.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PedestrianReport {
+    pub spawned: i32,
+    pub crossed: usize,
+    /// Time spent on the sidewalk before crossing.
+    pub wait_time: TimeSummary,
+    /// Pairs of a pedestrian and a vehicle that came closer than `CONFLICT_DISTANCE`.
+    pub conflicts: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        config::Config,
+        roads::{ Direction, Line },
+        vehicle_type::VehicleKind,
+        vehicles_management::VehiclesManagement,
+    };
+
+    /// Simulation with a car spawned on the West approach, going straight.
+    fn with_car() -> VehiclesManagement {
+        let mut vehicles = VehiclesManagement::with_config(0, &Config::default());
+        let line = Line::new(RoadDirection::West, 1, vec![Direction::Straight]);
+        vehicles.spawn_on(&line, Direction::Straight, VehicleKind::Car);
+        vehicles
+    }
+
+    /// Run `vehicles` until the car and the pedestrians are gone, checking every tick that
+    /// the car stays off the West crosswalk while a pedestrian walks over it, and return
+    /// whether the car stopped at the stop line.
+    fn run(vehicles: &mut VehiclesManagement) -> bool {
+        let crosswalk = *vehicles.pedestrians.crosswalk(RoadDirection::West);
+        let mut stopped = false;
+        while !vehicles.list.is_empty() || !vehicles.pedestrians.list.is_empty() {
+            assert!(vehicles.clock.tick < 10_000, "the car or the pedestrian never got through");
+            vehicles.update();
+            let is_walking = vehicles.pedestrians.list.iter().any(Pedestrian::is_crossing);
+            assert!(
+                !is_walking || vehicles.list.iter().all(|vehicle| !crosswalk.overlaps(vehicle)),
+                "the car is on the crosswalk with a pedestrian at tick {}",
+                vehicles.clock.tick
+            );
+            stopped |= vehicles.list.iter().any(|car| car.is_stopped_at(&vehicles.stop_area));
+        }
+        assert_eq!(vehicles.pedestrians.crossed, 1);
+        assert!(vehicles.pedestrians.conflicts.is_empty());
+        stopped
+    }
+
+    #[test]
+    fn vehicle_waits_for_a_pedestrian_on_the_crosswalk() {
+        let mut vehicles = with_car();
+        vehicles.pedestrians.spawn(RoadDirection::West, true, &vehicles.clock);
+        assert!(run(&mut vehicles));
+        assert_eq!(vehicles.pedestrians.waits, [0]);
+    }
+
+    #[test]
+    fn pedestrian_waits_for_a_vehicle_too_close_to_stop() {
+        let mut vehicles = with_car();
+        let crosswalk = *vehicles.pedestrians.crosswalk(RoadDirection::West);
+        while !is_committed(&vehicles.list[0], &crosswalk, &vehicles.stop_area) {
+            vehicles.update();
+        }
+        vehicles.pedestrians.spawn(RoadDirection::West, true, &vehicles.clock);
+        assert!(!run(&mut vehicles));
+        assert!(vehicles.pedestrians.waits[0] > 0);
+    }
+}
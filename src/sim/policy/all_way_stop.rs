@@ -0,0 +1,140 @@
+use crate::sim::{
+    clock::Clock,
+    roads::RoadIntersection,
+    vehicle::Vehicle,
+};
+
+use super::{ reactive::reactive_velocity, IntersectionPolicy };
+
+/// Legacy all-way stop: every vehicle comes to a full stop at the intersection edge, then
+/// vehicles are let in one at a time, first arrived first served.
+#[derive(Debug, Default)]
+pub struct AllWayStopPolicy {
+    /// Vehicles stopped at the edge, in arrival order.
+    queue: Vec<i32>,
+    /// Vehicle currently allowed in the intersection, and whether it already entered it.
+    crossing: Option<(i32, bool)>,
+    /// Vehicles held back on the last tick, skipped when letting the next one in.
+    held: Vec<i32>,
+}
+
+impl AllWayStopPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IntersectionPolicy for AllWayStopPolicy {
+    fn name(&self) -> &'static str {
+        "all-way-stop"
+    }
+
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64> {
+        self.queue.retain(|id| vehicles.iter().any(|vehicle| vehicle.id == *id));
+        if let Some((id, entered)) = self.crossing {
+            let inside = vehicles
+                .iter()
+                .find(|vehicle| vehicle.id == id)
+                .map(|vehicle| intersection.overlaps(vehicle));
+            self.crossing = match inside {
+                Some(true) => Some((id, true)),
+                Some(false) if !entered => Some((id, false)),
+                _ => None,
+            };
+        }
+
+        let mut velocities = vec![];
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            let velocity = reactive_velocity(vehicles, i, intersection, clock.dt);
+            let crossing_velocity = vehicle.profile.normal.min(
+                vehicle.velocity_to_follow(vehicles, clock.dt)
+            );
+
+            if self.crossing.is_some_and(|(id, _)| id == vehicle.id) {
+                velocities.push(crossing_velocity);
+                continue;
+            }
+            if !vehicle.is_at_stop_line(intersection, velocity, clock.dt) {
+                velocities.push(velocity);
+                continue;
+            }
+
+            // A vehicle joins the queue once it came to a full stop at the edge
+            let stop_velocity = velocity.min(vehicle.velocity_to_stop_at(intersection, clock.dt));
+            if !self.queue.contains(&vehicle.id) {
+                if vehicle.is_stopped_at(intersection) {
+                    self.queue.push(vehicle.id);
+                }
+                velocities.push(stop_velocity);
+            } else if
+                self.crossing.is_none() &&
+                self.queue.iter().find(|id| !self.held.contains(id)) == Some(&vehicle.id)
+            {
+                self.queue.retain(|id| *id != vehicle.id);
+                self.crossing = Some((vehicle.id, false));
+                velocities.push(crossing_velocity);
+            } else {
+                velocities.push(stop_velocity);
+            }
+        }
+        velocities
+    }
+
+    /// A held vehicle that was let in goes back to the head of the queue, and lets the
+    /// next one in while it is held.
+    fn hold_back(&mut self, held: &[i32]) {
+        self.held = held.to_vec();
+        if let Some((id, false)) = self.crossing {
+            if held.contains(&id) {
+                self.queue.insert(0, id);
+                self.crossing = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        config::Config,
+        roads::{ Direction, RoadDirection },
+        testing::{ car, run },
+    };
+
+    #[test]
+    fn vehicles_stop_then_cross_one_at_a_time_in_arrival_order() {
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let arrivals = vec![
+            (0, car(0, &config, RoadDirection::North, Direction::Straight)),
+            (20, car(1, &config, RoadDirection::West, Direction::Left)),
+            (40, car(2, &config, RoadDirection::South, Direction::Straight)),
+        ];
+        let mut stops = vec![];
+        let mut entries = vec![];
+        run(&mut AllWayStopPolicy::new(), &config, arrivals, |_, vehicles| {
+            let inside: Vec<i32> = vehicles
+                .iter()
+                .filter(|vehicle| stop_area.overlaps(vehicle))
+                .map(|vehicle| vehicle.id)
+                .collect();
+            assert!(inside.len() <= 1, "{:?} are in the intersection together", inside);
+            for vehicle in vehicles {
+                if vehicle.is_stopped_at(&stop_area) && !stops.contains(&vehicle.id) {
+                    stops.push(vehicle.id);
+                }
+                if inside.contains(&vehicle.id) && !entries.contains(&vehicle.id) {
+                    entries.push(vehicle.id);
+                }
+            }
+        });
+        assert_eq!(stops.len(), 3);
+        assert_eq!(entries, stops);
+    }
+}
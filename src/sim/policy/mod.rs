@@ -0,0 +1,74 @@
+use std::fmt::Debug;
+
+use super::{
+    clock::Clock,
+    geometry::Geometry,
+    roads::{ Line, RoadIntersection },
+    vehicle::Vehicle,
+};
+
+pub mod all_way_stop;
+pub mod priority_right;
+pub mod reactive;
+pub mod reservation;
+pub mod traffic_light;
+
+pub use all_way_stop::AllWayStopPolicy;
+pub use priority_right::PriorityToTheRightPolicy;
+pub use reactive::ReactivePolicy;
+pub use reservation::ReservationPolicy;
+pub use traffic_light::{ Signal, SignalTimings, TrafficLightPolicy };
+
+/// Right-of-way strategy of the intersection.
+///
+/// Every tick `VehiclesManagement` hands the policy the current vehicles and gets back the
+/// velocity each of them aims for during that tick (same order as `vehicles`), reached
+/// within the acceleration limits of its `DrivingProfile`. Statistics are collected outside
+/// the policy, so different policies can be compared on equal terms. Emergency vehicles
+/// and pedestrians are given the right of way over whatever the policy decides (see
+/// `EmergencyPreemption` and `Pedestrians`).
+pub trait IntersectionPolicy: Debug {
+    /// Name used to select the policy at startup.
+    fn name(&self) -> &'static str;
+
+    /// Signal shown to `line` at its stop line, for policies that use traffic lights.
+    fn signal(&self, _line: &Line) -> Option<Signal> {
+        None
+    }
+
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64>;
+
+    /// Called after `velocities` with the vehicles held at the edge of the intersection for
+    /// an emergency vehicle or a pedestrian, or while they change lane, every tick: any right
+    /// of way granted to them that they haven't used yet is lost.
+    fn hold_back(&mut self, _held: &[i32]) {}
+}
+
+/// Names accepted by `by_name`, the first one being the default.
+pub const POLICY_NAMES: [&str; 5] = [
+    "reservation",
+    "reactive",
+    "traffic-light",
+    "all-way-stop",
+    "priority-right",
+];
+
+pub fn by_name(
+    name: &str,
+    geometry: &Geometry,
+    signal_timings: SignalTimings
+) -> Result<Box<dyn IntersectionPolicy>, String> {
+    match name {
+        "reservation" => Ok(Box::new(ReservationPolicy::new(geometry))),
+        "reactive" => Ok(Box::new(ReactivePolicy)),
+        "traffic-light" => Ok(Box::new(TrafficLightPolicy::new(signal_timings))),
+        "all-way-stop" => Ok(Box::new(AllWayStopPolicy::new())),
+        "priority-right" => Ok(Box::new(PriorityToTheRightPolicy::new())),
+        _ => Err(format!("unknown policy '{}' (expected one of: {})", name, POLICY_NAMES.join(", "))),
+    }
+}
@@ -0,0 +1,195 @@
+use crate::sim::{
+    clock::Clock,
+    roads::{ RoadDirection, RoadIntersection },
+    vehicle::Vehicle,
+};
+
+use super::{ reactive::reactive_velocity, IntersectionPolicy };
+
+/// Vehicles closer than this to the intersection on the approach to the right have priority,
+/// in meters.
+pub const YIELD_DISTANCE: f64 = 15.0;
+
+/// Legacy priority-to-the-right: a vehicle at the intersection edge yields to any vehicle
+/// approaching from its right, and waits while vehicles from another approach are crossing.
+/// When every waiting vehicle has someone on its right, the first arrived goes.
+#[derive(Debug, Default)]
+pub struct PriorityToTheRightPolicy {
+    /// Vehicles allowed in the intersection: id, approach, and whether it already entered.
+    crossing: Vec<(i32, RoadDirection, bool)>,
+    /// Vehicles held back on the last tick, passed over when everyone yields.
+    held: Vec<i32>,
+    /// Vehicles that reached the intersection edge and haven't entered it yet, with the tick
+    /// they reached it.
+    arrivals: Vec<(i32, u64)>,
+}
+
+impl PriorityToTheRightPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IntersectionPolicy for PriorityToTheRightPolicy {
+    fn name(&self) -> &'static str {
+        "priority-right"
+    }
+
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64> {
+        self.crossing = self.crossing
+            .iter()
+            .filter_map(|&(id, approach, entered)| {
+                let vehicle = vehicles.iter().find(|vehicle| vehicle.id == id)?;
+                match intersection.overlaps(vehicle) {
+                    true => Some((id, approach, true)),
+                    false if !entered => Some((id, approach, false)),
+                    false => None,
+                }
+            })
+            .collect();
+        self.arrivals.retain(|(id, _)| {
+            vehicles.iter().any(|vehicle| vehicle.id == *id) &&
+                !self.crossing.iter().any(|&(crossing, _, entered)| crossing == *id && entered)
+        });
+
+        let mut velocities = vec![];
+        let mut waiting = vec![];
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            let velocity = reactive_velocity(vehicles, i, intersection, clock.dt);
+
+            let crossing_velocity = vehicle.profile.normal.min(
+                vehicle.velocity_to_follow(vehicles, clock.dt)
+            );
+            if self.crossing.iter().any(|(id, ..)| *id == vehicle.id) {
+                velocities.push(crossing_velocity);
+                continue;
+            }
+            if !vehicle.is_at_stop_line(intersection, velocity, clock.dt) {
+                velocities.push(velocity);
+                continue;
+            }
+            if !self.arrivals.iter().any(|(id, _)| *id == vehicle.id) {
+                self.arrivals.push((vehicle.id, clock.tick));
+            }
+
+            let approach = vehicle.road_direction;
+            let is_crossing_clear = self.crossing
+                .iter()
+                .all(|(_, other_approach, _)| *other_approach == approach);
+            let is_right_clear = vehicles.iter().all(|other| {
+                other.road_direction != approach.on_the_right() ||
+                    other
+                        .distance_to_intersection(intersection)
+                        .is_none_or(|distance| distance > YIELD_DISTANCE)
+            });
+
+            if is_crossing_clear && is_right_clear {
+                self.crossing.push((vehicle.id, approach, false));
+                velocities.push(crossing_velocity);
+            } else {
+                waiting.push(i);
+                velocities.push(velocity.min(vehicle.velocity_to_stop_at(intersection, clock.dt)));
+            }
+        }
+
+        // Everyone yields to someone: let the first arrived go, the lowest id among those
+        // arrived on the same tick
+        if self.crossing.is_empty() {
+            let arrival = |id: i32| {
+                self.arrivals
+                    .iter()
+                    .find(|(other, _)| *other == id)
+                    .map_or(clock.tick, |&(_, tick)| tick)
+            };
+            if
+                let Some(&i) = waiting
+                    .iter()
+                    .filter(|&&i| !self.held.contains(&vehicles[i].id))
+                    .min_by_key(|&&i| (arrival(vehicles[i].id), vehicles[i].id))
+            {
+                self.crossing.push((vehicles[i].id, vehicles[i].road_direction, false));
+                velocities[i] = vehicles[i].profile.normal.min(
+                    vehicles[i].velocity_to_follow(vehicles, clock.dt)
+                );
+            }
+        }
+        velocities
+    }
+
+    fn hold_back(&mut self, held: &[i32]) {
+        self.held = held.to_vec();
+        self.crossing.retain(|(id, _, entered)| *entered || !held.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ config::Config, roads::Direction, testing::{ car, run } };
+
+    /// Order in which the vehicles of `arrivals` enter the intersection under the policy,
+    /// and those which stopped at the edge first, checking that only vehicles of one
+    /// approach are in it at a time.
+    fn entries(arrivals: Vec<(u64, Vehicle)>) -> (Vec<i32>, Vec<i32>) {
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let mut entries = vec![];
+        let mut stopped = vec![];
+        run(&mut PriorityToTheRightPolicy::new(), &config, arrivals, |clock, vehicles| {
+            let inside: Vec<&Vehicle> = vehicles
+                .iter()
+                .filter(|vehicle| stop_area.overlaps(vehicle))
+                .collect();
+            assert!(
+                inside.iter().all(|vehicle| vehicle.road_direction == inside[0].road_direction),
+                "vehicles of several approaches in the intersection at tick {}",
+                clock.tick
+            );
+            for vehicle in vehicles {
+                if vehicle.is_stopped_at(&stop_area) && !stopped.contains(&vehicle.id) {
+                    stopped.push(vehicle.id);
+                }
+            }
+            for vehicle in inside {
+                if !entries.contains(&vehicle.id) {
+                    entries.push(vehicle.id);
+                }
+            }
+        });
+        (entries, stopped)
+    }
+
+    #[test]
+    fn vehicle_yields_to_the_one_on_its_right() {
+        let config = Config::default();
+        // East is on the right of North: the car from North reaches the intersection first,
+        // the East approach being longer, but lets the other one go
+        let arrivals = vec![
+            (0, car(0, &config, RoadDirection::East, Direction::Straight)),
+            (60, car(1, &config, RoadDirection::North, Direction::Straight)),
+        ];
+        assert_eq!(entries(arrivals), (vec![0, 1], vec![1]));
+    }
+
+    #[test]
+    fn vehicles_from_every_approach_do_not_lock_each_other_up() {
+        let config = Config::default();
+        // Each car has another one on its right when it reaches the intersection
+        let arrivals = [
+            RoadDirection::North,
+            RoadDirection::West,
+            RoadDirection::South,
+            RoadDirection::East,
+        ]
+            .into_iter()
+            .enumerate()
+            .map(|(id, road)| (0, car(id as i32, &config, road, Direction::Straight)))
+            .collect();
+        assert_eq!(entries(arrivals).0.len(), 4);
+    }
+}
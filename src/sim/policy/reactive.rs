@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::sim::{
+    car_following::{ idm_velocity, CarFollowing },
+    clock::Clock,
+    roads::RoadIntersection,
+    vehicle::Vehicle,
+};
+
+use super::IntersectionPolicy;
+
+/// The original car-following rules: each vehicle speeds up, slows down or brakes to a stop
+/// depending on who is in front of it. Nothing coordinates the crossing itself.
+#[derive(Debug, Default)]
+pub struct ReactivePolicy;
+
+impl IntersectionPolicy for ReactivePolicy {
+    fn name(&self) -> &'static str {
+        "reactive"
+    }
+
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64> {
+        (0..vehicles.len())
+            .map(|i| reactive_velocity(vehicles, i, intersection, clock.dt))
+            .collect()
+    }
+}
+
+/// Velocity the car-following model of `vehicles[i]` gives to it. The rules, also used
+/// by every vehicle in or past the intersection: fast on a clear road, slow once the vehicle
+/// ahead is within the distance needed to stop from the normal velocity, and never more
+/// than what still allows a comfortable stop behind it, for a tick of `dt`.
+pub fn reactive_velocity(
+    vehicles: &[Vehicle],
+    i: usize,
+    intersection: &RoadIntersection,
+    dt: Duration
+) -> f64 {
+    let vehicle = &vehicles[i];
+    let profile = &vehicle.profile;
+    let leader = vehicle.leader(vehicles);
+    if
+        profile.car_following == CarFollowing::Idm &&
+        vehicle.distance_to_intersection(intersection).is_some()
+    {
+        return idm_velocity(vehicle, leader, dt);
+    }
+    let Some((gap, _)) = leader else {
+        return profile.fast;
+    };
+
+    let slowing_distance = profile.braking_distance(profile.normal) + profile.safe_distance * 3.0;
+    let velocity = if gap < slowing_distance { profile.slow } else { profile.normal };
+    velocity.min(vehicle.velocity_to_stop_within(gap - profile.safe_distance, dt))
+}
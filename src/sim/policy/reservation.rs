@@ -0,0 +1,206 @@
+use crate::sim::{
+    clock::Clock,
+    geometry::Geometry,
+    reservation::ReservationManager,
+    roads::RoadIntersection,
+    vehicle::Vehicle,
+};
+
+use super::{ reactive::reactive_velocity, IntersectionPolicy };
+
+/// Default policy: vehicles follow the car-following rules on the roads, and may only
+/// enter the intersection once the `ReservationManager` granted them a crossing, which
+/// they make aiming for their normal velocity. Without a reservation they brake to stop at
+/// the intersection edge.
+#[derive(Debug)]
+pub struct ReservationPolicy {
+    pub reservations: ReservationManager,
+    /// Vehicles held back on the last tick, which don't ask for a reservation.
+    held: Vec<i32>,
+}
+
+impl ReservationPolicy {
+    pub fn new(geometry: &Geometry) -> Self {
+        ReservationPolicy { reservations: ReservationManager::new(geometry), held: vec![] }
+    }
+
+    /// Vehicles a crossing may end up following or being followed by: those granted a
+    /// crossing so far, including on this tick, and those past the edge of `intersection`.
+    fn crossing(&self, vehicles: &[Vehicle], intersection: &RoadIntersection) -> Vec<Vehicle> {
+        vehicles
+            .iter()
+            .filter(|vehicle| {
+                self.reservations.get(vehicle.id).is_some() ||
+                    vehicle.distance_to_intersection(intersection).is_none()
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+impl IntersectionPolicy for ReservationPolicy {
+    fn name(&self) -> &'static str {
+        "reservation"
+    }
+
+    /// Admission control at the intersection edge: a crossing is only granted with room
+    /// behind the vehicle ahead, and once a vehicle holds a reservation it crosses at the
+    /// reserved velocity. Should the vehicle ahead slow down more than planned, or the vehicle
+    /// fall behind its plan, the rest of the crossing is planned again. If that is refused, a
+    /// vehicle that has not reached the intersection yet brakes to stop at the edge, and one
+    /// already crossing holds on to the cells it actually needs.
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64> {
+        let now = clock.tick;
+        let late: Vec<i32> = vehicles
+            .iter()
+            .filter(|vehicle| {
+                self.reservations.get(vehicle.id).is_some() &&
+                    !self.reservations.is_on_plan(vehicle, now)
+            })
+            .map(|vehicle| vehicle.id)
+            .collect();
+        self.reservations.release_expired(vehicles, now);
+
+        let mut velocities = vec![];
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            // The vehicles crossing its path are kept apart by the reservations, only those
+            // it follows slow it down
+            let ahead: Vec<Vehicle> = vehicles
+                .iter()
+                .filter(|other| vehicle.heads_same_way(other))
+                .cloned()
+                .collect();
+            let following = vehicle.velocity_to_follow(&ahead, clock.dt);
+            if let Some(reservation) = self.reservations.get(vehicle.id).copied() {
+                let velocity = reservation.velocity.min(following);
+                if
+                    (!late.contains(&vehicle.id) && reservation.velocity <= following) ||
+                    self.reservations.replan(
+                        vehicle,
+                        &self.crossing(vehicles, intersection),
+                        velocity,
+                        clock
+                    )
+                {
+                    velocities.push(velocity);
+                    continue;
+                }
+                if vehicle.distance_to_intersection(intersection).is_none() {
+                    // Already crossing and late on its reservation: it can't stop anymore,
+                    // and only keeps pace with the vehicle ahead, holding the cells it needs
+                    let pace = vehicle
+                        .leader(&ahead)
+                        .map_or(following, |(_, leader)| leader.velocity.max(following));
+                    velocities.push(
+                        self.reservations.hold(vehicle, reservation.velocity.min(pace), clock)
+                    );
+                    continue;
+                }
+                self.reservations.cancel(vehicle.id);
+            }
+
+            let mut velocity = reactive_velocity(vehicles, i, intersection, clock.dt);
+            if vehicle.is_at_stop_line(intersection, velocity, clock.dt) {
+                let crossing_velocity = vehicle.profile.normal.min(following);
+                velocity = if
+                    !self.held.contains(&vehicle.id) &&
+                    self.reservations.request(
+                        vehicle,
+                        &self.crossing(vehicles, intersection),
+                        crossing_velocity,
+                        clock
+                    )
+                {
+                    crossing_velocity
+                } else {
+                    velocity.min(vehicle.velocity_to_stop_at(intersection, clock.dt))
+                };
+            }
+            velocities.push(velocity);
+        }
+        velocities
+    }
+
+    fn hold_back(&mut self, held: &[i32]) {
+        self.held = held.to_vec();
+        for &vehicle_id in held {
+            self.reservations.cancel(vehicle_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ config::Config, roads::{ Direction, RoadDirection }, testing::car };
+
+    #[test]
+    fn late_vehicle_keeps_the_tiles_it_is_on() {
+        let config = Config::default();
+        let geometry = config.geometry;
+        let stop_area = geometry.stop_area();
+        let mut clock = Clock::new(config.tick_duration);
+        let mut policy = ReservationPolicy::new(&geometry);
+        let grid = policy.reservations.grid;
+
+        let mut vehicles = vec![car(0, &config, RoadDirection::West, Direction::Straight)];
+        let mut stalled = 0;
+        while clock.tick < 2000 {
+            if clock.tick == 60 {
+                vehicles.push(car(1, &config, RoadDirection::North, Direction::Straight));
+                vehicles.push(car(2, &config, RoadDirection::South, Direction::Left));
+                vehicles.push(car(3, &config, RoadDirection::West, Direction::Straight));
+            }
+            let mut velocities = policy.velocities(&vehicles, &stop_area, &clock);
+            policy.hold_back(&[]);
+            // The first car stalls for a while in the middle of the intersection, falling
+            // behind its plan while the others come up to the intersection
+            let is_in_box = vehicles
+                .first()
+                .is_some_and(|first| first.id == 0 && geometry.intersection().overlaps(first));
+            if is_in_box && stalled < 50 {
+                stalled += 1;
+                velocities[0] = 0.0;
+            }
+            for (vehicle, velocity) in vehicles.iter_mut().zip(velocities) {
+                vehicle.accelerate(velocity, clock.dt);
+                vehicle.step(clock.dt);
+            }
+            clock.advance();
+
+            // Past the stop line, every vehicle in the grid holds a reservation and none
+            // shares a tile with another
+            let crossing: Vec<&Vehicle> = vehicles
+                .iter()
+                .filter(|vehicle| vehicle.distance_to_intersection(&stop_area).is_none())
+                .filter(|vehicle| !grid.footprint(vehicle).is_empty())
+                .collect();
+            for (i, vehicle) in crossing.iter().enumerate() {
+                assert!(
+                    policy.reservations.get(vehicle.id).is_some(),
+                    "vehicle {} is crossing without a reservation at tick {}",
+                    vehicle.id,
+                    clock.tick
+                );
+                let tiles = grid.footprint(vehicle);
+                for other in &crossing[i + 1..] {
+                    assert!(
+                        grid.footprint(other).iter().all(|tile| !tiles.contains(tile)),
+                        "vehicles {} and {} share a tile at tick {}",
+                        vehicle.id,
+                        other.id,
+                        clock.tick
+                    );
+                }
+            }
+            vehicles.retain(|vehicle| geometry.is_on_screen(vehicle));
+        }
+        assert_eq!(stalled, 50);
+        assert!(vehicles.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use std::time::Duration;
+use serde::{ Deserialize, Serialize };
+
+use crate::sim::{
+    clock::Clock,
+    roads::{ Line, RoadDirection, RoadIntersection },
+    vehicle::Vehicle,
+};
+
+use super::{ reactive::reactive_velocity, IntersectionPolicy };
+
+/// Order in which the approaches get the green light.
+pub const PHASES: [RoadDirection; 4] = [
+    RoadDirection::North,
+    RoadDirection::West,
+    RoadDirection::South,
+    RoadDirection::East,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Signal {
+    Green,
+    Amber,
+    Red,
+}
+
+/// Durations of one phase: green, then amber, then all-red clearance before the next
+/// approach turns green.
+#[derive(Debug, Clone, Copy)]
+pub struct SignalTimings {
+    pub green: Duration,
+    pub amber: Duration,
+    pub red: Duration,
+}
+
+impl SignalTimings {
+    pub fn phase(&self) -> Duration {
+        self.green + self.amber + self.red
+    }
+}
+
+impl Default for SignalTimings {
+    fn default() -> Self {
+        SignalTimings {
+            green: Duration::from_secs(6),
+            amber: Duration::from_secs(2),
+            red: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Conventional fixed-time four-phase signal, the baseline smart policies are compared
+/// against. Each approach gets the green for all of its lines in turn; vehicles follow the
+/// car-following rules and brake to wait at the stop line unless their line is green. On
+/// amber, a vehicle too close to stop comfortably goes on. The next approach only gets the
+/// green once the vehicles of the previous phase left the intersection: the all-red
+/// clearance lasts until then.
+#[derive(Debug)]
+pub struct TrafficLightPolicy {
+    pub timings: SignalTimings,
+    /// Time the signals are at, lagging behind the clock by the clearances extended so far.
+    elapsed: Duration,
+    extension: Duration,
+}
+
+impl TrafficLightPolicy {
+    pub fn new(timings: SignalTimings) -> Self {
+        TrafficLightPolicy { timings, elapsed: Duration::ZERO, extension: Duration::ZERO }
+    }
+
+    /// Number of phases begun before `time`, the first one included.
+    fn phase_count(&self, time: Duration) -> u128 {
+        match self.timings.phase().as_nanos() {
+            0 => 0,
+            phase => time.as_nanos() / phase,
+        }
+    }
+
+    /// Current signal shown to the lines of `road_direction`, red for every approach when
+    /// the timings give no green time.
+    pub fn signal_at(&self, road_direction: RoadDirection) -> Signal {
+        let phase = self.timings.phase();
+        if self.timings.green.is_zero() {
+            return Signal::Red;
+        }
+        let cycle_time = self.elapsed.as_nanos() % (phase.as_nanos() * (PHASES.len() as u128));
+        let current = (cycle_time / phase.as_nanos()) as usize;
+        if PHASES[current] != road_direction {
+            return Signal::Red;
+        }
+
+        let phase_time = Duration::from_nanos((cycle_time % phase.as_nanos()) as u64);
+        if phase_time < self.timings.green {
+            Signal::Green
+        } else if phase_time < self.timings.green + self.timings.amber {
+            Signal::Amber
+        } else {
+            Signal::Red
+        }
+    }
+}
+
+impl IntersectionPolicy for TrafficLightPolicy {
+    fn name(&self) -> &'static str {
+        "traffic-light"
+    }
+
+    fn signal(&self, line: &Line) -> Option<Signal> {
+        Some(self.signal_at(line.road_direction))
+    }
+
+    fn velocities(
+        &mut self,
+        vehicles: &[Vehicle],
+        intersection: &RoadIntersection,
+        clock: &Clock
+    ) -> Vec<f64> {
+        // Hold the signals at the end of the phase while a vehicle is still inside
+        let time = clock.now().saturating_sub(self.extension);
+        let is_occupied = vehicles.iter().any(|vehicle| {
+            vehicle.distance_to_intersection(intersection).is_none() &&
+                intersection.overlaps(vehicle)
+        });
+        if is_occupied && self.phase_count(time) != self.phase_count(self.elapsed) {
+            self.extension = clock.now().saturating_sub(self.elapsed);
+        } else {
+            self.elapsed = time;
+        }
+
+        let mut velocities = vec![];
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            let velocity = reactive_velocity(vehicles, i, intersection, clock.dt);
+            let must_stop = match self.signal_at(vehicle.road_direction) {
+                Signal::Green => false,
+                Signal::Amber =>
+                    vehicle
+                        .distance_to_intersection(intersection)
+                        .is_some_and(|distance| {
+                            vehicle.stopping_distance(vehicle.profile.comfortable_deceleration) <=
+                                distance
+                        }),
+                Signal::Red => true,
+            };
+            if must_stop && vehicle.is_at_stop_line(intersection, velocity, clock.dt) {
+                velocities.push(velocity.min(vehicle.velocity_to_stop_at(intersection, clock.dt)));
+            } else {
+                velocities.push(velocity);
+            }
+        }
+        velocities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ config::Config, roads::Direction, testing::car };
+
+    /// Signals of `PHASES` after `seconds` of simulated time, with `vehicles` on the roads.
+    fn signals_after(
+        policy: &mut TrafficLightPolicy,
+        seconds: u64,
+        vehicles: &[Vehicle]
+    ) -> Vec<Signal> {
+        let mut clock = Clock::new(Duration::from_secs(1));
+        clock.tick = seconds;
+        policy.velocities(vehicles, &Config::default().geometry.stop_area(), &clock);
+        PHASES.iter().map(|&road_direction| policy.signal_at(road_direction)).collect()
+    }
+
+    #[test]
+    fn approaches_get_the_green_in_turn() {
+        use Signal::*;
+        // 6 s of green, 2 s of amber and 3 s of all-red per approach
+        let mut policy = TrafficLightPolicy::new(SignalTimings::default());
+        let expected = [
+            (0, [Green, Red, Red, Red]),
+            (5, [Green, Red, Red, Red]),
+            (6, [Amber, Red, Red, Red]),
+            (8, [Red, Red, Red, Red]),
+            (11, [Red, Green, Red, Red]),
+            (17, [Red, Amber, Red, Red]),
+            (22, [Red, Red, Green, Red]),
+            (33, [Red, Red, Red, Green]),
+            (41, [Red, Red, Red, Red]),
+            (44, [Green, Red, Red, Red]),
+        ];
+        for (seconds, signals) in expected {
+            assert_eq!(signals_after(&mut policy, seconds, &[]), signals, "after {}s", seconds);
+        }
+    }
+
+    #[test]
+    fn no_green_time_keeps_every_approach_red() {
+        let timings = SignalTimings { green: Duration::ZERO, ..SignalTimings::default() };
+        let mut policy = TrafficLightPolicy::new(timings);
+        for seconds in [0, 6, 11, 30] {
+            assert_eq!(signals_after(&mut policy, seconds, &[]), [Signal::Red; 4]);
+        }
+    }
+
+    #[test]
+    fn next_approach_waits_for_the_intersection_to_clear() {
+        use Signal::*;
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let mut policy = TrafficLightPolicy::new(SignalTimings::default());
+        // A vehicle of the North approach still in the middle of the intersection when the
+        // all-red clearance ends, 11 s into the cycle
+        let mut vehicle = car(0, &config, RoadDirection::North, Direction::Straight);
+        vehicle.y = stop_area.y + stop_area.height / 2.0;
+        let inside = [vehicle];
+        for seconds in 8..20 {
+            let signals = signals_after(&mut policy, seconds, &inside);
+            assert_eq!(signals, [Red; 4], "after {}s", seconds);
+        }
+        // Then the West approach gets its full green once it left
+        assert_eq!(signals_after(&mut policy, 20, &[]), [Red, Green, Red, Red]);
+        assert_eq!(signals_after(&mut policy, 25, &[]), [Red, Green, Red, Red]);
+        assert_eq!(signals_after(&mut policy, 26, &[]), [Red, Amber, Red, Red]);
+    }
+
+    #[test]
+    fn vehicle_waits_at_the_stop_line_for_the_green() {
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let mut clock = Clock::new(config.tick_duration);
+        let mut policy = TrafficLightPolicy::new(SignalTimings::default());
+        // The East approach is the last to get the green, 33 s into the cycle
+        let mut vehicle = car(0, &config, RoadDirection::East, Direction::Straight);
+        let mut has_stopped = false;
+        while vehicle.distance_to_intersection(&stop_area).is_some() {
+            assert!(clock.tick < 10_000, "the vehicle never entered the intersection");
+            let vehicles = std::slice::from_ref(&vehicle);
+            let velocity = policy.velocities(vehicles, &stop_area, &clock)[0];
+            vehicle.accelerate(velocity, clock.dt);
+            vehicle.step(clock.dt);
+            has_stopped |= vehicle.velocity == 0.0;
+            clock.advance();
+        }
+        assert!(has_stopped);
+        assert_eq!(policy.signal_at(RoadDirection::East), Signal::Green);
+        assert!(clock.now() >= Duration::from_secs(33));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{ fs::{ self, File }, io::{ BufWriter, Write }, path::Path, time::Duration };
+use serde::{ Deserialize, Serialize };
+
+use super::{
+    geometry::Geometry,
+    pedestrians::Pedestrian,
+    policy::{ IntersectionPolicy, Signal },
+    roads::{ Direction, Line },
+    vehicle::Vehicle,
+    vehicle_type::VehicleKind,
+};
+
+/// Parameters of a recorded session, needed to render it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub seed: u64,
+    pub policy: String,
+    pub dt: Duration,
+    pub geometry: Geometry,
+    pub approaches: Vec<Vec<Line>>,
+}
+
+/// Vehicle added to the simulation, by a key press, `spawn_random` or a scenario.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnRecord {
+    pub tick: u64,
+    pub id: i32,
+    pub line: Line,
+    pub direction: Direction,
+    pub kind: VehicleKind,
+}
+
+/// State of the simulation at the end of a tick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub tick: u64,
+    pub vehicles: Vec<Vehicle>,
+    pub pedestrians: Vec<Pedestrian>,
+    /// Signal shown on every line of the session approaches, in order.
+    pub signals: Vec<Option<Signal>>,
+}
+
+/// One line of a recording file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Entry {
+    Session(Session),
+    Spawn(SpawnRecord),
+    Frame(Frame),
+}
+
+/// Writes a session to a file, one JSON entry per line: the `Session` first, then the
+/// spawns and frames as they happen.
+#[derive(Debug)]
+pub struct Recorder {
+    writer: BufWriter<File>,
+    lines: Vec<Line>,
+    /// First write error, reported by `finish`.
+    error: Option<String>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, session: &Session) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e|
+            format!("cannot create recording '{}': {}", path.display(), e)
+        )?;
+        let mut recorder = Recorder {
+            writer: BufWriter::new(file),
+            lines: session.approaches.iter().flatten().cloned().collect(),
+            error: None,
+        };
+        recorder.write(&Entry::Session(session.clone()));
+        match recorder.error.take() {
+            Some(e) => Err(format!("cannot write recording '{}': {}", path.display(), e)),
+            None => Ok(recorder),
+        }
+    }
+
+    pub fn spawn(&mut self, record: SpawnRecord) {
+        self.write(&Entry::Spawn(record));
+    }
+
+    pub fn frame(
+        &mut self,
+        tick: u64,
+        vehicles: &[Vehicle],
+        pedestrians: &[Pedestrian],
+        policy: &dyn IntersectionPolicy
+    ) {
+        let signals = self.lines
+            .iter()
+            .map(|line| policy.signal(line))
+            .collect();
+        self.write(
+            &Entry::Frame(Frame {
+                tick,
+                vehicles: vehicles.to_vec(),
+                pedestrians: pedestrians.to_vec(),
+                signals,
+            })
+        );
+    }
+
+    /// Flush the file and report the first error met while recording.
+    pub fn finish(mut self) -> Result<(), String> {
+        if self.error.is_none() {
+            if let Err(e) = self.writer.flush() {
+                self.error = Some(e.to_string());
+            }
+        }
+        match self.error {
+            Some(e) => Err(format!("recording failed: {}", e)),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, entry: &Entry) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut self.writer, entry)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writeln!(self.writer).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// A recording loaded back, to be scrubbed through frame by frame.
+#[derive(Debug)]
+pub struct Replay {
+    pub session: Session,
+    pub spawns: Vec<SpawnRecord>,
+    /// Frames sorted by tick.
+    pub frames: Vec<Frame>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e|
+            format!("cannot read recording '{}': {}", path.display(), e)
+        )?;
+        Replay::parse(&text).map_err(|e| format!("invalid recording '{}': {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut session = None;
+        let mut spawns = vec![];
+        let mut frames: Vec<Frame> = vec![];
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(line).map_err(|e|
+                format!("line {}: {}", i + 1, e)
+            )?;
+            match (entry, &session) {
+                (Entry::Session(header), None) => {
+                    session = Some(header);
+                }
+                (Entry::Session(_), Some(_)) => {
+                    return Err(format!("line {}: second session header", i + 1));
+                }
+                (_, None) => {
+                    return Err(format!("line {}: expected the session header first", i + 1));
+                }
+                (Entry::Spawn(record), Some(_)) => spawns.push(record),
+                (Entry::Frame(frame), Some(_)) => {
+                    if frames.last().is_some_and(|last| last.tick >= frame.tick) {
+                        return Err(format!("line {}: frame {} out of order", i + 1, frame.tick));
+                    }
+                    frames.push(frame);
+                }
+            }
+        }
+        let session = session.ok_or("empty recording")?;
+        Ok(Replay { session, spawns, frames })
+    }
+
+    /// Signal shown on `line` in `frame`.
+    pub fn signal(&self, frame: &Frame, line: &Line) -> Option<Signal> {
+        let index = self.session.approaches
+            .iter()
+            .flatten()
+            .position(|other| other == line)?;
+        frame.signals.get(index).copied().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::roads::RoadDirection;
+
+    fn line(entry: &Entry) -> String {
+        serde_json::to_string(entry).unwrap()
+    }
+
+    fn session() -> String {
+        line(
+            &Entry::Session(Session {
+                seed: 7,
+                policy: "reservation".to_string(),
+                dt: Duration::from_millis(16),
+                geometry: Geometry::default(),
+                approaches: vec![],
+            })
+        )
+    }
+
+    fn frame(tick: u64) -> String {
+        line(&Entry::Frame(Frame { tick, vehicles: vec![], pedestrians: vec![], signals: vec![] }))
+    }
+
+    fn spawn(tick: u64) -> String {
+        line(
+            &Entry::Spawn(SpawnRecord {
+                tick,
+                id: 0,
+                line: Line::new(RoadDirection::North, 1, vec![Direction::Straight]),
+                direction: Direction::Straight,
+                kind: VehicleKind::Car,
+            })
+        )
+    }
+
+    #[test]
+    fn parses_a_recording() {
+        let text = [session(), spawn(0), frame(0), String::new(), frame(1)].join("\n");
+        let replay = Replay::parse(&text).unwrap();
+        assert_eq!(replay.session.seed, 7);
+        assert_eq!(replay.session.policy, "reservation");
+        assert_eq!(replay.spawns.len(), 1);
+        assert_eq!(replay.frames.iter().map(|frame| frame.tick).collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn rejects_invalid_recordings() {
+        let error = |lines: &[String]| Replay::parse(&lines.join("\n")).unwrap_err();
+        assert_eq!(error(&[]), "empty recording");
+        assert!(error(&[frame(0)]).contains("line 1: expected the session header first"));
+        assert!(error(&[session(), session()]).contains("line 2: second session header"));
+        assert!(error(&[session(), frame(2), frame(1)]).contains("line 3: frame 1 out of order"));
+        assert!(error(&[session(), frame(2), frame(2)]).contains("line 3: frame 2 out of order"));
+        assert!(error(&[session(), "{\"frame\":".to_string()]).starts_with("line 2: "));
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use super::{ clock::Clock, geometry::Geometry, roads::RoadIntersection, vehicle::Vehicle };
+
+/// Upper bound on the ticks simulated when planning a crossing, or checking that it is
+/// clear. A vehicle that would not leave the intersection by then (e.g. at velocity 0) is
+/// refused the crossing.
+pub(crate) const MAX_PLAN_TICKS: u64 = 1000;
+
+/// Shares of its velocity tried in turn by a vehicle that holds on to the rest of its
+/// crossing, down to standing still.
+const HOLD_VELOCITY_SHARES: [f64; 5] = [1.0, 0.75, 0.5, 0.25, 0.0];
+
+/// A tile of the grid at a tick: column, row and tick.
+type Cell = (i32, i32, u64);
+
+/// A granted crossing: the vehicle must aim for `velocity` until `exit_tick`, the last tick
+/// its footprint overlaps the tile grid.
+#[derive(Debug, Clone, Copy)]
+pub struct Reservation {
+    pub velocity: f64,
+    pub entry_tick: u64,
+    pub exit_tick: u64,
+}
+
+/// Grid of square tiles over an area of the crossroads, the intersection box by default.
+#[derive(Debug, Clone, Copy)]
+pub struct TileGrid {
+    /// Side of one tile in meters (one lane wide).
+    pub tile_size: f64,
+    /// Top-left corner of the grid, in meters.
+    pub x: f64,
+    pub y: f64,
+    pub cols: i32,
+    pub rows: i32,
+}
+
+impl TileGrid {
+    /// Tiles one lane wide over the intersection box of `geometry`.
+    pub fn new(geometry: &Geometry) -> Self {
+        Self::over(&geometry.intersection(), geometry.to_meters(geometry.lane_width))
+    }
+
+    /// Tiles one lane wide over the intersection box of `geometry` and one more all around
+    /// it, which the ends of long vehicles sweep over as they turn.
+    pub fn around(geometry: &Geometry) -> Self {
+        let grid = Self::new(geometry);
+        TileGrid {
+            x: grid.x - grid.tile_size,
+            y: grid.y - grid.tile_size,
+            cols: grid.cols + 2,
+            rows: grid.rows + 2,
+            ..grid
+        }
+    }
+
+    /// Tiles of `tile_size` meters over `area`.
+    pub fn over(area: &RoadIntersection, tile_size: f64) -> Self {
+        TileGrid {
+            tile_size,
+            x: area.x,
+            y: area.y,
+            cols: (area.width / tile_size).round() as i32,
+            rows: (area.height / tile_size).round() as i32,
+        }
+    }
+
+    /// Tiles covered by the bounding box of the footprint of `vehicle`.
+    pub fn footprint(&self, vehicle: &Vehicle) -> Vec<(i32, i32)> {
+        let (left, top, right, bottom) = vehicle.bounding_box();
+        // Tolerance so that rounding errors don't widen a footprint touching a tile edge
+        let first = |low: f64, origin: f64| ((low - origin) / self.tile_size + 1e-6).floor() as i32;
+        let last = |high: f64, origin: f64| ((high - origin) / self.tile_size - 1e-6).floor() as i32;
+        let first_col = first(left, self.x).max(0);
+        let last_col = last(right, self.x).min(self.cols - 1);
+        let first_row = first(top, self.y).max(0);
+        let last_row = last(bottom, self.y).min(self.rows - 1);
+
+        let mut tiles = vec![];
+        for col in first_col..=last_col {
+            for row in first_row..=last_row {
+                tiles.push((col, row));
+            }
+        }
+        tiles
+    }
+
+    /// Center of the tile (`col`, `row`), in meters.
+    pub fn center(&self, col: i32, row: i32) -> (f64, f64) {
+        (
+            self.x + ((col as f64) + 0.5) * self.tile_size,
+            self.y + ((row as f64) + 0.5) * self.tile_size,
+        )
+    }
+}
+
+/// AIM-style intersection manager. The intersection and its edges are divided into a grid
+/// of tiles and each approaching vehicle asks for the (tile, tick) cells its footprint will
+/// cover on the way through. A request is only granted if none of those cells is already held
+/// by another vehicle, so two admitted vehicles can never occupy the same tile at the same tick.
+/// A vehicle that falls behind its plan once in the grid keeps its reservation, its cells
+/// being moved to where it actually goes (see `hold`), until it has left the grid.
+#[derive(Debug)]
+pub struct ReservationManager {
+    pub grid: TileGrid,
+    tiles: HashMap<Cell, i32>,
+    reservations: HashMap<i32, Reservation>,
+}
+
+impl ReservationManager {
+    pub fn new(geometry: &Geometry) -> Self {
+        ReservationManager {
+            grid: TileGrid::around(geometry),
+            tiles: HashMap::new(),
+            reservations: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, vehicle_id: i32) -> Option<&Reservation> {
+        self.reservations.get(&vehicle_id)
+    }
+
+    /// Ask for a crossing aiming for `velocity` from the current one, starting with the move
+    /// made at the current tick of `clock`. `vehicles` are the ones it may end up following
+    /// through the intersection. Returns whether the reservation was granted.
+    pub fn request(
+        &mut self,
+        vehicle: &Vehicle,
+        vehicles: &[Vehicle],
+        velocity: f64,
+        clock: &Clock
+    ) -> bool {
+        if self.reservations.contains_key(&vehicle.id) {
+            return true;
+        }
+        self.replan(vehicle, vehicles, velocity, clock)
+    }
+
+    /// Replace the reservation of `vehicle`, if any, with a crossing planned as in `request`.
+    /// The former reservation is kept if the new one is refused.
+    pub fn replan(
+        &mut self,
+        vehicle: &Vehicle,
+        vehicles: &[Vehicle],
+        velocity: f64,
+        clock: &Clock
+    ) -> bool {
+        let Some((cells, mut reservation)) = self.plan(vehicle, vehicles, velocity, clock) else {
+            return false;
+        };
+        // A vehicle already in the grid entered it with its former reservation
+        if let Some(former) = self.get(vehicle.id) {
+            reservation.entry_tick = reservation.entry_tick.min(former.entry_tick);
+        }
+        self.cancel(vehicle.id);
+        for cell in cells {
+            self.tiles.insert(cell, vehicle.id);
+        }
+        self.reservations.insert(vehicle.id, reservation);
+        true
+    }
+
+    /// Follow the vehicle through the intersection and collect the cells it covers, `None`
+    /// if one is held by another vehicle, or if the vehicle would come closer to one of
+    /// `vehicles` driving ahead of it the same way than it can stop. They are assumed to
+    /// keep to their reservation or, without one, to their current velocity.
+    fn plan(
+        &self,
+        vehicle: &Vehicle,
+        vehicles: &[Vehicle],
+        velocity: f64,
+        clock: &Clock
+    ) -> Option<(Vec<Cell>, Reservation)> {
+        let now = clock.tick;
+        let mut ghost = vehicle.clone();
+        let mut others: Vec<(Vehicle, f64)> = vehicles
+            .iter()
+            .filter(|other| other.id != vehicle.id)
+            .map(|other| {
+                let velocity = self.get(other.id).map_or(other.velocity, |reservation| {
+                    reservation.velocity
+                });
+                (other.clone(), velocity)
+            })
+            .collect();
+        let mut cells = vec![];
+        let mut entry_tick = None;
+        let mut exit_tick = None;
+        for tick in now + 1..=now + MAX_PLAN_TICKS {
+            ghost.accelerate(velocity, clock.dt);
+            ghost.step(clock.dt);
+            for (other, other_velocity) in &mut others {
+                other.accelerate(*other_velocity, clock.dt);
+                other.step(clock.dt);
+                if
+                    ghost.heads_same_way(other) &&
+                    (has_to_slow_down(&ghost, velocity, other, clock) ||
+                        has_to_slow_down(other, *other_velocity, &ghost, clock))
+                {
+                    return None;
+                }
+            }
+            let tiles = self.grid.footprint(&ghost);
+            if tiles.is_empty() {
+                if entry_tick.is_some() {
+                    exit_tick = Some(tick - 1);
+                    break;
+                }
+                continue;
+            }
+            entry_tick.get_or_insert(tick);
+            for (col, row) in tiles {
+                if self.tiles.get(&(col, row, tick)).is_some_and(|&id| id != vehicle.id) {
+                    return None;
+                }
+                cells.push((col, row, tick));
+            }
+        }
+
+        Some((cells, Reservation { velocity, entry_tick: entry_tick?, exit_tick: exit_tick? }))
+    }
+
+    /// Reserve the rest of the crossing of `vehicle`, already in the grid but unable to keep
+    /// to its reservation, and return the velocity it must aim for. It goes ahead of the
+    /// vehicles that haven't reached the grid yet: those holding a cell it needs lose their
+    /// reservation and have to stop. The cells held by the vehicles already in the grid are
+    /// left to them, the vehicle slowing down from `velocity` as much as it takes to keep
+    /// clear of them. Should it not leave the grid within the planning horizon, it holds its
+    /// cells up to the horizon.
+    pub fn hold(&mut self, vehicle: &Vehicle, velocity: f64, clock: &Clock) -> f64 {
+        let now = clock.tick;
+        let is_free = |cells: &[Cell]| {
+            cells.iter().all(|cell| {
+                self.tiles.get(cell).is_none_or(|&id| id == vehicle.id || self.is_pending(id, now))
+            })
+        };
+        let (velocity, (cells, exit_tick)) = HOLD_VELOCITY_SHARES.iter()
+            .map(|share| velocity * share)
+            .map(|velocity| (velocity, self.sweep(vehicle, velocity, clock)))
+            .find(|(_, (cells, _))| is_free(cells))
+            .unwrap_or_else(|| (velocity, self.sweep(vehicle, velocity, clock)));
+
+        let entry_tick = self.get(vehicle.id).map_or(now, |reservation| reservation.entry_tick);
+        self.cancel(vehicle.id);
+        let mut overtaken = vec![];
+        for cell in cells {
+            match self.tiles.get(&cell).copied() {
+                Some(id) if !self.is_pending(id, now) => {
+                    continue;
+                }
+                Some(id) if !overtaken.contains(&id) => overtaken.push(id),
+                _ => {}
+            }
+            self.tiles.insert(cell, vehicle.id);
+        }
+        for id in overtaken {
+            self.cancel(id);
+        }
+        self.reservations.insert(vehicle.id, Reservation { velocity, entry_tick, exit_tick });
+        velocity
+    }
+
+    /// Cells covered by `vehicle` aiming for `velocity` until it leaves the grid, and the
+    /// last tick it covers one of them.
+    fn sweep(&self, vehicle: &Vehicle, velocity: f64, clock: &Clock) -> (Vec<Cell>, u64) {
+        let now = clock.tick;
+        let mut ghost = vehicle.clone();
+        let mut cells = vec![];
+        for tick in now + 1..=now + MAX_PLAN_TICKS {
+            ghost.accelerate(velocity, clock.dt);
+            ghost.step(clock.dt);
+            let tiles = self.grid.footprint(&ghost);
+            if tiles.is_empty() {
+                return (cells, tick - 1);
+            }
+            cells.extend(tiles.into_iter().map(|(col, row)| (col, row, tick)));
+        }
+        (cells, now + MAX_PLAN_TICKS)
+    }
+
+    /// Whether the reservation of `vehicle_id` has yet to start at tick `now`.
+    fn is_pending(&self, vehicle_id: i32, now: u64) -> bool {
+        self.get(vehicle_id).is_some_and(|reservation| reservation.entry_tick > now)
+    }
+
+    /// Whether `vehicle` is where its reservation expects it at tick `now`: outside the grid
+    /// before its entry tick or after its exit tick, on cells it holds in between.
+    pub fn is_on_plan(&self, vehicle: &Vehicle, now: u64) -> bool {
+        let Some(reservation) = self.get(vehicle.id) else {
+            return false;
+        };
+        let tiles = self.grid.footprint(vehicle);
+        if tiles.is_empty() {
+            return now < reservation.entry_tick || now > reservation.exit_tick;
+        }
+        tiles.iter().all(|&(col, row)| self.tiles.get(&(col, row, now)) == Some(&vehicle.id))
+    }
+
+    /// Give up the reservation of `vehicle_id` and the cells it holds.
+    pub fn cancel(&mut self, vehicle_id: i32) {
+        if self.reservations.remove(&vehicle_id).is_some() {
+            self.tiles.retain(|_, id| *id != vehicle_id);
+        }
+    }
+
+    /// Drop the cells of ticks already simulated and the reservations of vehicles that have
+    /// left the grid. Among `vehicles`, the ones still in the grid keep their reservation
+    /// past its exit tick.
+    pub fn release_expired(&mut self, vehicles: &[Vehicle], now: u64) {
+        self.tiles.retain(|&(_, _, tick), _| tick > now);
+        let grid = self.grid;
+        self.reservations.retain(|&id, reservation| {
+            reservation.exit_tick >= now ||
+                vehicles
+                    .iter()
+                    .any(|vehicle| vehicle.id == id && !grid.footprint(vehicle).is_empty())
+        });
+    }
+}
+
+/// Whether `vehicle`, aiming for `velocity`, is closer to `leader` ahead of it than it
+/// needs to stop from that velocity, and so would have to slow down.
+fn has_to_slow_down(vehicle: &Vehicle, velocity: f64, leader: &Vehicle, clock: &Clock) -> bool {
+    vehicle.gap_to(leader).is_some_and(|gap| {
+        let room = gap - vehicle.profile.safe_distance;
+        velocity > vehicle.velocity_to_stop_within(room, clock.dt)
+    })
+}
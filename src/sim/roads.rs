@@ -1,8 +1,13 @@
 use std::vec;
-use sdl2::{ pixels::Color, rect::Point, render::Canvas, video::Window };
-use crate::{ WINDOW_HEIGHT, WINDOW_WIDTH };
+use serde::{ Deserialize, Serialize };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use super::vehicle::Vehicle;
+
+/// Depth of a crosswalk, in meters.
+pub const CROSSWALK_WIDTH: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RoadDirection {
     North,
     West,
@@ -10,7 +15,56 @@ pub enum RoadDirection {
     East,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+impl RoadDirection {
+    /// Approach whose traffic comes from the right of a vehicle on this approach.
+    pub fn on_the_right(self) -> RoadDirection {
+        match self {
+            RoadDirection::North => RoadDirection::East,
+            RoadDirection::West => RoadDirection::North,
+            RoadDirection::South => RoadDirection::West,
+            RoadDirection::East => RoadDirection::South,
+        }
+    }
+
+    /// Approach facing this one across the intersection.
+    pub fn opposite(self) -> RoadDirection {
+        match self {
+            RoadDirection::North => RoadDirection::South,
+            RoadDirection::West => RoadDirection::East,
+            RoadDirection::South => RoadDirection::North,
+            RoadDirection::East => RoadDirection::West,
+        }
+    }
+
+    /// Direction of travel of the vehicles of this approach, in degrees clockwise from the
+    /// x axis of the screen (East is 0, North, driving down the screen, is 90).
+    pub fn heading(self) -> f64 {
+        match self {
+            RoadDirection::East => 0.0,
+            RoadDirection::North => 90.0,
+            RoadDirection::West => 180.0,
+            RoadDirection::South => 270.0,
+        }
+    }
+
+    /// Road a vehicle of this approach drives on once it took `direction`.
+    pub fn after_turn(self, direction: Direction) -> RoadDirection {
+        match (self, direction) {
+            (_, Direction::Straight) => self,
+            (RoadDirection::North, Direction::Left) => RoadDirection::East,
+            (RoadDirection::North, Direction::Right) => RoadDirection::West,
+            (RoadDirection::West, Direction::Left) => RoadDirection::North,
+            (RoadDirection::West, Direction::Right) => RoadDirection::South,
+            (RoadDirection::South, Direction::Left) => RoadDirection::West,
+            (RoadDirection::South, Direction::Right) => RoadDirection::East,
+            (RoadDirection::East, Direction::Left) => RoadDirection::South,
+            (RoadDirection::East, Direction::Right) => RoadDirection::North,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     Left,
     Straight,
@@ -27,66 +81,92 @@ impl Road {
     pub fn new() -> Self {
         Road { lines1: vec![], lines2: vec![] }
     }
+}
 
-    pub fn render(canvas: &mut Canvas<Window>) {
-        // Render road border
-        canvas.set_draw_color(Color::RGB(250, 250, 250));
-        canvas.draw_line(Point::new(362, 0), Point::new(362, 234)).unwrap();
-        canvas.draw_line(Point::new(662, 0), Point::new(662, 234)).unwrap();
-
-        canvas.draw_line(Point::new(662, 234), Point::new(WINDOW_WIDTH, 234)).unwrap();
-        canvas.draw_line(Point::new(662, 534), Point::new(WINDOW_WIDTH, 534)).unwrap();
-
-        canvas.draw_line(Point::new(662, 534), Point::new(662, WINDOW_HEIGHT)).unwrap();
-        canvas.draw_line(Point::new(362, 534), Point::new(362, WINDOW_HEIGHT)).unwrap();
-
-        canvas.draw_line(Point::new(362, 534), Point::new(0, 534)).unwrap();
-        canvas.draw_line(Point::new(362, 234), Point::new(0, 234)).unwrap();
-        // canvas.set_draw_color(Color::RGB(250, 250, 250));
-        // canvas.draw_rect(Rect::new(362, 0, 300, WINDOW_HEIGHT as u32)).unwrap();
-        // canvas.draw_rect(Rect::new(0, 234, WINDOW_WIDTH as u32, 300)).unwrap();
-
-        // Render lines
-        // Verticals
-        // canvas.set_draw_color(Color::RGB(0, 0, 100));
-        // canvas.draw_line(Point::new(412, 0), Point::new(412, WINDOW_HEIGHT)).unwrap();
-        // canvas.draw_line(Point::new(462, 0), Point::new(462, WINDOW_HEIGHT)).unwrap();
-        // canvas.draw_line(Point::new(512, 0), Point::new(512, WINDOW_HEIGHT)).unwrap();
-        // canvas.draw_line(Point::new(562, 0), Point::new(562, WINDOW_HEIGHT)).unwrap();
-        // canvas.draw_line(Point::new(612, 0), Point::new(612, WINDOW_HEIGHT)).unwrap();
-
-        //Horizontals
-        // canvas.set_draw_color(Color::RGB(0, 100, 0));
-        // canvas.draw_line(Point::new(0, 284), Point::new(WINDOW_WIDTH, 284)).unwrap();
-        // canvas.draw_line(Point::new(0, 334), Point::new(WINDOW_WIDTH, 334)).unwrap();
-        // canvas.draw_line(Point::new(0, 384), Point::new(WINDOW_WIDTH, 384)).unwrap();
-        // canvas.draw_line(Point::new(0, 434), Point::new(WINDOW_WIDTH, 434)).unwrap();
-        // canvas.draw_line(Point::new(0, 484), Point::new(WINDOW_WIDTH, 484)).unwrap();
+impl Default for Road {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// One lane of an approach and the manoeuvres permitted from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Line {
     pub road_direction: RoadDirection,
-    pub direction: Direction,
+    /// Lane index counted from the outer edge of the road (0 is the right-most lane).
+    pub lane: i32,
+    pub directions: Vec<Direction>,
 }
 
 impl Line {
-    pub fn new(road_direction: RoadDirection, direction: Direction) -> Self {
-        Line { road_direction, direction }
+    pub fn new(road_direction: RoadDirection, lane: i32, directions: Vec<Direction>) -> Self {
+        Line { road_direction, lane, directions }
+    }
+
+    /// The default three lines of the approach coming from `road_direction`: right turns on
+    /// the outer lane, straight in the middle and left turns on the inner lane.
+    pub fn approach(road_direction: RoadDirection) -> Vec<Line> {
+        vec![
+            Line::new(road_direction, 2, vec![Direction::Left]),
+            Line::new(road_direction, 1, vec![Direction::Straight]),
+            Line::new(road_direction, 0, vec![Direction::Right])
+        ]
     }
 }
 
+/// Box of the intersection, in meters.
 #[derive(Debug)]
 pub struct RoadIntersection {
-    pub x: i32,
-    pub y: i32,
-    pub width: i32,
-    pub height: i32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
 }
 
 impl RoadIntersection {
-    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
         RoadIntersection { x, y, width, height }
     }
+
+    /// Whether the bounding box of the body of `vehicle` overlaps the intersection.
+    pub fn overlaps(&self, vehicle: &Vehicle) -> bool {
+        let (left, top, right, bottom) = vehicle.bounding_box();
+        right > self.x &&
+            left < self.x + self.width &&
+            bottom > self.y &&
+            top < self.y + self.height
+    }
+}
+
+/// Zebra crossing over the road of an approach, both ways, just outside the edge of the
+/// intersection, in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Crosswalk {
+    /// Approach whose road the crosswalk crosses.
+    pub road: RoadDirection,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Crosswalk {
+    /// Whether the bounding box of the body of `vehicle` overlaps the crosswalk.
+    pub fn overlaps(&self, vehicle: &Vehicle) -> bool {
+        let (left, top, right, bottom) = vehicle.bounding_box();
+        right > self.x &&
+            left < self.x + self.width &&
+            bottom > self.y &&
+            top < self.y + self.height
+    }
+
+    /// Whether `vehicle` has the crosswalk ahead on its way: the one of its approach until
+    /// it reached `stop_area`, the one of the road it leaves by until it left it.
+    pub fn is_ahead_of(&self, vehicle: &Vehicle, stop_area: &RoadIntersection) -> bool {
+        let exit = vehicle.road_direction.after_turn(vehicle.direction).opposite();
+        match vehicle.distance_to_intersection(stop_area) {
+            Some(_) => self.road == vehicle.road_direction || self.road == exit,
+            None => self.road == exit && stop_area.overlaps(vehicle),
+        }
+    }
 }
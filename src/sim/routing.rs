@@ -0,0 +1,351 @@
+use std::collections::BTreeSet;
+
+use super::{
+    geometry::Geometry,
+    network::NetworkLayout,
+    roads::{ Direction, Line, RoadDirection },
+};
+
+/// Point of a `RoadGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Node {
+    /// Intersection at an index of the `Network`.
+    Intersection(usize),
+    /// Start of the approach of an intersection whose vehicles come from outside the grid.
+    Entry(usize, RoadDirection),
+    /// End of the road the vehicles of an approach leave an intersection by, out of the grid.
+    Exit(usize, RoadDirection),
+}
+
+/// Lane segment from one node of a `RoadGraph` to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: Node,
+    pub to: Node,
+    /// Approach the vehicles of the lane drive as.
+    pub road_direction: RoadDirection,
+    /// Lane the segment ends in, the vehicles changing lane on the way between two
+    /// intersections.
+    pub lane: i32,
+    /// Manoeuvres permitted at the end of the lane, none when it leaves the grid.
+    pub directions: Vec<Direction>,
+    /// In meters.
+    pub length: f64,
+}
+
+/// Manoeuvre of a route at one intersection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub intersection: usize,
+    /// Approach the vehicle arrives on.
+    pub road_direction: RoadDirection,
+    pub direction: Direction,
+}
+
+/// Shortest path between two nodes of a `RoadGraph`, as the manoeuvre taken at each
+/// intersection on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub origin: Node,
+    pub destination: Node,
+    pub steps: Vec<Step>,
+    /// In meters, from the start of the first lane to the end of the last one.
+    pub length: f64,
+}
+
+/// Roads of a `Network`: its intersections, the entries and exits at the edge of the grid,
+/// and the lane segments between them.
+///
+/// A route only goes through the manoeuvres the lanes permit, a vehicle being free to
+/// change lane between two intersections but not on its way out of the grid.
+#[derive(Debug, Clone)]
+pub struct RoadGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    geometry: Geometry,
+}
+
+impl RoadGraph {
+    /// Graph of the grid of `layout`, every intersection having the `approaches` of the
+    /// crossroads described by `geometry`.
+    pub fn new(layout: &NetworkLayout, approaches: &[Vec<Line>], geometry: &Geometry) -> Self {
+        let geometry = *geometry;
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for index in 0..layout.rows * layout.columns {
+            let to = Node::Intersection(index);
+            nodes.push(to);
+            for lines in approaches {
+                let road = lines[0].road_direction;
+                let approach = approach_length(&geometry, road);
+                let (from, length) = match layout.next(index, road.opposite()) {
+                    Some(previous) => {
+                        let exit = approach_length(&geometry, road.opposite());
+                        (Node::Intersection(previous), exit + approach)
+                    }
+                    None => {
+                        nodes.push(Node::Entry(index, road));
+                        (Node::Entry(index, road), approach)
+                    }
+                };
+                for line in lines {
+                    edges.push(Edge {
+                        from,
+                        to,
+                        road_direction: road,
+                        lane: line.lane,
+                        directions: line.directions.clone(),
+                        length,
+                    });
+                }
+            }
+
+            for road in [
+                RoadDirection::North,
+                RoadDirection::West,
+                RoadDirection::South,
+                RoadDirection::East,
+            ] {
+                if layout.next(index, road).is_some() {
+                    continue;
+                }
+                // Lanes of the road turned or driven into
+                let lanes: BTreeSet<i32> = approaches
+                    .iter()
+                    .flatten()
+                    .filter(|line| {
+                        line.directions
+                            .iter()
+                            .any(|&direction| line.road_direction.after_turn(direction) == road)
+                    })
+                    .map(|line| line.lane)
+                    .collect();
+                if lanes.is_empty() {
+                    continue;
+                }
+                let exit = Node::Exit(index, road);
+                nodes.push(exit);
+                for lane in lanes {
+                    edges.push(Edge {
+                        from: to,
+                        to: exit,
+                        road_direction: road,
+                        lane,
+                        directions: vec![],
+                        length: approach_length(&geometry, road.opposite()),
+                    });
+                }
+            }
+        }
+        RoadGraph { nodes, edges, geometry }
+    }
+
+    /// Where vehicles come into the grid.
+    pub fn entries(&self) -> Vec<Node> {
+        self.nodes.iter().copied().filter(|node| matches!(node, Node::Entry(..))).collect()
+    }
+
+    /// Where vehicles leave the grid.
+    pub fn exits(&self) -> Vec<Node> {
+        self.nodes.iter().copied().filter(|node| matches!(node, Node::Exit(..))).collect()
+    }
+
+    /// Shortest route from the entry `origin` to the exit `destination`, `None` when no
+    /// sequence of permitted manoeuvres leads there.
+    pub fn route(&self, origin: Node, destination: Node) -> Option<Route> {
+        let starts = (0..self.edges.len())
+            .filter(|&edge| self.edges[edge].from == origin)
+            .collect();
+        self.shortest(origin, starts, destination)
+    }
+
+    /// Shortest route to `destination` of a vehicle arriving at the intersection at
+    /// `intersection` in one of `lanes` of the `road_direction` approach.
+    pub fn route_on(
+        &self,
+        intersection: usize,
+        road_direction: RoadDirection,
+        lanes: &[i32],
+        destination: Node
+    ) -> Option<Route> {
+        let origin = Node::Intersection(intersection);
+        let starts = (0..self.edges.len())
+            .filter(|&edge| {
+                let edge = &self.edges[edge];
+                edge.to == origin &&
+                    edge.road_direction == road_direction &&
+                    lanes.contains(&edge.lane)
+            })
+            .collect();
+        self.shortest(origin, starts, destination)
+    }
+
+    /// Dijkstra's algorithm over the lanes, from the ends of the `starts` lanes.
+    fn shortest(&self, origin: Node, starts: Vec<usize>, destination: Node) -> Option<Route> {
+        let count = self.edges.len();
+        let mut cost = vec![f64::INFINITY; count];
+        let mut done = vec![false; count];
+        // Lane driven before each lane and the manoeuvre between them
+        let mut previous: Vec<Option<(usize, Direction)>> = vec![None; count];
+        for start in starts {
+            cost[start] = self.edges[start].length;
+        }
+        loop {
+            let current = (0..count)
+                .filter(|&edge| !done[edge] && cost[edge].is_finite())
+                .min_by(|&a, &b| cost[a].total_cmp(&cost[b]))?;
+            done[current] = true;
+            let edge = &self.edges[current];
+            if edge.to == destination {
+                let mut steps = vec![];
+                let mut lane = current;
+                while let Some((before, direction)) = previous[lane] {
+                    let Node::Intersection(intersection) = self.edges[before].to else {
+                        break;
+                    };
+                    steps.push(Step {
+                        intersection,
+                        road_direction: self.edges[before].road_direction,
+                        direction,
+                    });
+                    lane = before;
+                }
+                steps.reverse();
+                return Some(Route { origin, destination, steps, length: cost[current] });
+            }
+            if !matches!(edge.to, Node::Intersection(_)) {
+                continue;
+            }
+            for &direction in &edge.directions {
+                let road = edge.road_direction.after_turn(direction);
+                let through = self.crossing_length(edge, direction);
+                for (next, lane) in self.edges.iter().enumerate() {
+                    let is_exit = matches!(lane.to, Node::Exit(..));
+                    if
+                        lane.from != edge.to ||
+                        lane.road_direction != road ||
+                        (is_exit && lane.lane != edge.lane)
+                    {
+                        continue;
+                    }
+                    let total = cost[current] + through + lane.length;
+                    if total < cost[next] {
+                        cost[next] = total;
+                        previous[next] = Some((current, direction));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Distance driven through the intersection from the end of `edge` taking `direction`.
+    fn crossing_length(&self, edge: &Edge, direction: Direction) -> f64 {
+        let line = Line::new(edge.road_direction, edge.lane, vec![direction]);
+        match self.geometry.turn_path(&line, direction, 0.0) {
+            Some(path) => path.length(),
+            None => {
+                let intersection = self.geometry.intersection();
+                match edge.road_direction {
+                    RoadDirection::North | RoadDirection::South => intersection.height,
+                    RoadDirection::West | RoadDirection::East => intersection.width,
+                }
+            }
+        }
+    }
+}
+
+/// Distance from where the vehicles of `road` enter the area of an intersection to the
+/// intersection, in meters.
+fn approach_length(geometry: &Geometry, road: RoadDirection) -> f64 {
+    let intersection = geometry.intersection();
+    let (left, top) = (geometry.to_meters(geometry.area_x), geometry.to_meters(geometry.area_y));
+    let (right, bottom) = (
+        left + geometry.to_meters(geometry.window_width),
+        top + geometry.to_meters(geometry.window_height),
+    );
+    match road {
+        RoadDirection::North => intersection.y - top,
+        RoadDirection::South => bottom - intersection.y - intersection.height,
+        RoadDirection::West => right - intersection.x - intersection.width,
+        RoadDirection::East => intersection.x - left,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::config::Config;
+
+    fn layout(rows: usize, columns: usize) -> NetworkLayout {
+        NetworkLayout { rows, columns, policies: vec![] }
+    }
+
+    fn step(intersection: usize, road_direction: RoadDirection, direction: Direction) -> Step {
+        Step { intersection, road_direction, direction }
+    }
+
+    #[test]
+    fn straight_across_the_grid() {
+        let approaches = Config::default().approaches;
+        let graph = RoadGraph::new(&layout(2, 2), &approaches, &Geometry::default());
+        let route = graph
+            .route(Node::Entry(0, RoadDirection::North), Node::Exit(2, RoadDirection::North))
+            .unwrap();
+        assert_eq!(
+            route.steps,
+            vec![
+                step(0, RoadDirection::North, Direction::Straight),
+                step(2, RoadDirection::North, Direction::Straight)
+            ]
+        );
+        assert!(route.length > 0.0);
+    }
+
+    #[test]
+    fn turn_at_a_single_intersection() {
+        let approaches = Config::default().approaches;
+        let graph = RoadGraph::new(&layout(1, 1), &approaches, &Geometry::default());
+        let exit = RoadDirection::North.after_turn(Direction::Right);
+        let route = graph.route(Node::Entry(0, RoadDirection::North), Node::Exit(0, exit)).unwrap();
+        assert_eq!(route.steps, vec![step(0, RoadDirection::North, Direction::Right)]);
+    }
+
+    #[test]
+    fn every_step_leads_to_the_next() {
+        let layout = layout(2, 2);
+        let approaches = Config::default().approaches;
+        let graph = RoadGraph::new(&layout, &approaches, &Geometry::default());
+        let route = graph
+            .route(Node::Entry(0, RoadDirection::North), Node::Exit(3, RoadDirection::East))
+            .unwrap();
+        let first = route.steps.first().unwrap();
+        assert_eq!((first.intersection, first.road_direction), (0, RoadDirection::North));
+        for pair in route.steps.windows(2) {
+            let road = pair[0].road_direction.after_turn(pair[0].direction);
+            assert_eq!(pair[1].road_direction, road);
+            assert_eq!(layout.next(pair[0].intersection, road), Some(pair[1].intersection));
+        }
+        let last = route.steps.last().unwrap();
+        assert_eq!(last.intersection, 3);
+        assert_eq!(last.road_direction.after_turn(last.direction), RoadDirection::East);
+    }
+
+    #[test]
+    fn no_route_without_the_manoeuvre() {
+        let approaches: Vec<Vec<Line>> = [
+            RoadDirection::North,
+            RoadDirection::West,
+            RoadDirection::South,
+            RoadDirection::East,
+        ]
+            .into_iter()
+            .map(|road| vec![Line::new(road, 0, vec![Direction::Straight])])
+            .collect();
+        let graph = RoadGraph::new(&layout(1, 1), &approaches, &Geometry::default());
+        assert!(graph.nodes.contains(&Node::Exit(0, RoadDirection::West)));
+        assert_eq!(
+            graph.route(Node::Entry(0, RoadDirection::North), Node::Exit(0, RoadDirection::West)),
+            None
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use std::{ collections::HashMap, time::Duration };
+use serde::{ Deserialize, Serialize };
+
+use super::{
+    clock::Clock,
+    geometry::Geometry,
+    reservation::TileGrid,
+    roads::RoadDirection,
+    trips::{ TimeSummary, Trip },
+    vehicle::Vehicle,
+};
+
+/// Time-to-collision beyond which two vehicles are not considered in conflict.
+const TTC_HORIZON: Duration = Duration::from_secs(10);
+
+/// Seconds between two positions of a pair of vehicles compared when looking for their
+/// time-to-collision, which is then narrowed down to `TTC_PRECISION`.
+const TTC_STEP: f64 = 0.05;
+const TTC_PRECISION: f64 = 0.001;
+
+/// Values of the surrogate safety measures below which (above which for DRAC) a pair of
+/// vehicles counts as a close call.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SafetyThresholds {
+    /// Time-to-collision, in seconds.
+    pub ttc: f64,
+    /// Post-encroachment time, in seconds.
+    pub pet: f64,
+    /// Deceleration rate to avoid the crash, in meters per second squared.
+    pub drac: f64,
+}
+
+impl Default for SafetyThresholds {
+    /// The DRAC threshold is above the comfortable deceleration of cars, which every
+    /// vehicle braking to a stop line applies.
+    fn default() -> Self {
+        SafetyThresholds { ttc: 1.5, pet: 1.0, drac: 4.0 }
+    }
+}
+
+/// Worst values of the safety measures between two vehicles (`first` < `second`).
+#[derive(Debug, Clone, Serialize)]
+pub struct PairRecord {
+    pub first: i32,
+    pub second: i32,
+    /// Seconds.
+    pub min_ttc: Option<f64>,
+    /// Seconds.
+    pub min_pet: Option<f64>,
+    /// Meters per second squared.
+    pub max_drac: Option<f64>,
+    pub close_call: bool,
+}
+
+/// Post-encroachment times measured on one tile of the intersection.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictPoint {
+    /// Center of the tile, in meters.
+    pub x: f64,
+    pub y: f64,
+    pub encroachments: usize,
+    /// Seconds.
+    pub min_pet: f64,
+}
+
+/// Surrogate safety measures of a run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SafetyReport {
+    pub thresholds: SafetyThresholds,
+    /// Pairs of vehicles with at least one measure.
+    pub conflicts: usize,
+    /// Pairs of vehicles beyond at least one threshold.
+    pub close_calls: usize,
+    pub min_ttc: Option<f64>,
+    pub min_pet: Option<f64>,
+    pub max_drac: Option<f64>,
+    /// Distribution of the minimum TTC of every pair.
+    pub ttc: TimeSummary,
+    /// Distribution of the minimum PET of every pair.
+    pub pet: TimeSummary,
+    pub pairs: Vec<PairRecord>,
+    pub conflict_points: Vec<ConflictPoint>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PairMeasures {
+    min_ttc: Option<f64>,
+    min_pet: Option<f64>,
+    max_drac: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct Tile {
+    /// Vehicles (id, origin) covering the tile at the previous tick.
+    occupants: Vec<(i32, RoadDirection)>,
+    /// Last vehicle to leave the tile and the tick it left.
+    last_exit: Option<(i32, RoadDirection, u64)>,
+    encroachments: usize,
+    min_pet: Option<f64>,
+}
+
+/// Measures, every tick, the time-to-collision (TTC) and deceleration rate to avoid the
+/// crash (DRAC) of every pair of vehicles on their current course, and the
+/// post-encroachment time (PET) on every tile of the intersection.
+///
+/// PET is only measured between vehicles from different approaches, vehicles following
+/// each other on the same path being no conflict.
+#[derive(Debug)]
+pub struct SafetyMonitor {
+    pub thresholds: SafetyThresholds,
+    grid: TileGrid,
+    tiles: HashMap<(i32, i32), Tile>,
+    pairs: HashMap<(i32, i32), PairMeasures>,
+}
+
+impl SafetyMonitor {
+    pub fn new(geometry: &Geometry, thresholds: SafetyThresholds) -> Self {
+        SafetyMonitor {
+            thresholds,
+            grid: TileGrid::new(geometry),
+            tiles: HashMap::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Take the measures of `vehicles` about to move with their current velocity.
+    pub fn observe(&mut self, vehicles: &[Vehicle], trips: &[Trip], clock: &Clock) {
+        for (i, a) in vehicles.iter().enumerate() {
+            for b in &vehicles[i + 1..] {
+                let Some(ttc) = time_to_collision(a, b) else {
+                    continue;
+                };
+                if ttc > TTC_HORIZON.as_secs_f64() {
+                    continue;
+                }
+                let measures = self.pairs.entry(pair(a.id, b.id)).or_default();
+                measures.min_ttc = Some(measures.min_ttc.map_or(ttc, |min| min.min(ttc)));
+                if ttc > 0.0 {
+                    // Constant deceleration stopping the closing motion over the distance left
+                    let (avx, avy) = a.velocity_vector();
+                    let (bvx, bvy) = b.velocity_vector();
+                    let closing = (bvx - avx).hypot(bvy - avy);
+                    let drac = closing / (2.0 * ttc);
+                    measures.max_drac = Some(measures.max_drac.map_or(drac, |max| max.max(drac)));
+                }
+            }
+        }
+
+        // Tiles covered now
+        let mut covered: HashMap<(i32, i32), Vec<(i32, RoadDirection)>> = HashMap::new();
+        for vehicle in vehicles {
+            let origin = trips[vehicle.id as usize].origin;
+            for tile in self.grid.footprint(vehicle) {
+                covered.entry(tile).or_default().push((vehicle.id, origin));
+            }
+        }
+        for position in covered.keys() {
+            self.tiles.entry(*position).or_default();
+        }
+        let now = clock.tick;
+        let mut encroachments = vec![];
+        for (position, tile) in &mut self.tiles {
+            let occupants = covered.remove(position).unwrap_or_default();
+            for &(id, origin) in &tile.occupants {
+                if !occupants.iter().any(|(other, _)| *other == id) {
+                    tile.last_exit = Some((id, origin, now));
+                }
+            }
+            for &(id, origin) in &occupants {
+                if tile.occupants.iter().any(|(other, _)| *other == id) {
+                    continue;
+                }
+                let inside = occupants
+                    .iter()
+                    .find(|(other, other_origin)| *other != id && *other_origin != origin);
+                let pet = match (inside, tile.last_exit) {
+                    (Some(&(other, _)), _) => Some((other, 0.0)),
+                    (None, Some((other, other_origin, exit))) if
+                        other != id &&
+                        other_origin != origin
+                    => {
+                        Some((other, clock.duration(now - exit).as_secs_f64()))
+                    }
+                    _ => None,
+                };
+                if let Some((other, pet)) = pet {
+                    tile.encroachments += 1;
+                    tile.min_pet = Some(tile.min_pet.map_or(pet, |min| min.min(pet)));
+                    encroachments.push((pair(id, other), pet));
+                }
+            }
+            tile.occupants = occupants;
+        }
+        for (key, pet) in encroachments {
+            let measures = self.pairs.entry(key).or_default();
+            measures.min_pet = Some(measures.min_pet.map_or(pet, |min| min.min(pet)));
+        }
+    }
+
+    fn is_close_call(&self, measures: &PairMeasures) -> bool {
+        measures.min_ttc.is_some_and(|ttc| ttc < self.thresholds.ttc) ||
+            measures.min_pet.is_some_and(|pet| pet < self.thresholds.pet) ||
+            measures.max_drac.is_some_and(|drac| drac > self.thresholds.drac)
+    }
+
+    /// Number of pairs of vehicles beyond at least one threshold so far.
+    pub fn close_calls(&self) -> usize {
+        self.pairs
+            .values()
+            .filter(|measures| self.is_close_call(measures))
+            .count()
+    }
+
+    pub fn report(&self) -> SafetyReport {
+        let mut pairs: Vec<PairRecord> = self.pairs
+            .iter()
+            .map(|(&(first, second), measures)| PairRecord {
+                first,
+                second,
+                min_ttc: measures.min_ttc,
+                min_pet: measures.min_pet,
+                max_drac: measures.max_drac,
+                close_call: self.is_close_call(measures),
+            })
+            .collect();
+        pairs.sort_by_key(|record| (record.first, record.second));
+
+        let mut conflict_points: Vec<ConflictPoint> = self.tiles
+            .iter()
+            .filter_map(|(&(col, row), tile)| {
+                let (x, y) = self.grid.center(col, row);
+                Some(ConflictPoint {
+                    x,
+                    y,
+                    encroachments: tile.encroachments,
+                    min_pet: tile.min_pet?,
+                })
+            })
+            .collect();
+        conflict_points.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+        let seconds = |value: f64| Duration::from_secs_f64(value);
+        SafetyReport {
+            thresholds: self.thresholds,
+            conflicts: pairs.len(),
+            close_calls: pairs
+                .iter()
+                .filter(|record| record.close_call)
+                .count(),
+            min_ttc: pairs
+                .iter()
+                .filter_map(|record| record.min_ttc)
+                .reduce(f64::min),
+            min_pet: pairs
+                .iter()
+                .filter_map(|record| record.min_pet)
+                .reduce(f64::min),
+            max_drac: pairs
+                .iter()
+                .filter_map(|record| record.max_drac)
+                .reduce(f64::max),
+            ttc: TimeSummary::new(pairs.iter().filter_map(|record| record.min_ttc.map(seconds))),
+            pet: TimeSummary::new(pairs.iter().filter_map(|record| record.min_pet.map(seconds))),
+            pairs,
+            conflict_points,
+        }
+    }
+}
+
+fn pair(a: i32, b: i32) -> (i32, i32) {
+    (a.min(b), a.max(b))
+}
+
+/// Seconds before the bodies of `a` and `b`, rotated with their heading, overlap if both
+/// keep their heading and velocity (0 if they already overlap), `None` if they never do
+/// within `TTC_HORIZON`.
+pub fn time_to_collision(a: &Vehicle, b: &Vehicle) -> Option<f64> {
+    // The bodies can only overlap while their bounding boxes do
+    let (avx, avy) = a.velocity_vector();
+    let (bvx, bvy) = b.velocity_vector();
+    let (a_left, a_top, a_right, a_bottom) = a.bounding_box();
+    let (b_left, b_top, b_right, b_bottom) = b.bounding_box();
+    let (x_start, x_end) = overlap_interval(
+        b_left - a_right,
+        b_right - a_left,
+        bvx - avx
+    )?;
+    let (y_start, y_end) = overlap_interval(
+        b_top - a_bottom,
+        b_bottom - a_top,
+        bvy - avy
+    )?;
+    let start = x_start.max(y_start).max(0.0);
+    let end = x_end.min(y_end).min(TTC_HORIZON.as_secs_f64());
+    if start >= end {
+        return None;
+    }
+
+    let overlap_after = |time: f64| moved(a, time).overlaps(&moved(b, time));
+    if overlap_after(start) {
+        return Some(start);
+    }
+    let mut clear = start;
+    while clear < end {
+        let time = (clear + TTC_STEP).min(end);
+        if overlap_after(time) {
+            // Narrow down the first overlap between the last clear position and this one
+            let mut hit = time;
+            while hit - clear > TTC_PRECISION {
+                let middle = (clear + hit) / 2.0;
+                if overlap_after(middle) {
+                    hit = middle;
+                } else {
+                    clear = middle;
+                }
+            }
+            return Some(hit);
+        }
+        clear = time;
+    }
+    None
+}
+
+/// `vehicle` after `time` seconds on its current heading at its current velocity.
+fn moved(vehicle: &Vehicle, time: f64) -> Vehicle {
+    let (vx, vy) = vehicle.velocity_vector();
+    Vehicle { x: vehicle.x + vx * time, y: vehicle.y + vy * time, ..vehicle.clone() }
+}
+
+/// Times during which two boxes overlap along an axis, given the offsets of the near edge
+/// (`low`) and far edge (`high`) of the second one from the far and near edges of the first
+/// one, the second one moving at `velocity` relative to the first one.
+fn overlap_interval(low: f64, high: f64, velocity: f64) -> Option<(f64, f64)> {
+    if velocity.abs() < 1e-9 {
+        return if low < 0.0 && high > 0.0 {
+            Some((f64::NEG_INFINITY, f64::INFINITY))
+        } else {
+            None
+        };
+    }
+    let t1 = -low / velocity;
+    let t2 = -high / velocity;
+    Some((t1.min(t2), t1.max(t2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ config::Config, roads::Direction, vehicle_type::VehicleKind };
+
+    /// Car centered on (`x`, `y`) heading `heading` degrees at `velocity`.
+    fn car(id: i32, (x, y): (f64, f64), heading: f64, velocity: f64) -> Vehicle {
+        let config = Config::default();
+        let car = config.vehicle_types.get(VehicleKind::Car);
+        let mut vehicle = Vehicle::new(id, RoadDirection::East, Direction::Straight, x, y, car);
+        vehicle.heading = heading;
+        vehicle.velocity = velocity;
+        vehicle
+    }
+
+    fn trips(origins: &[RoadDirection]) -> Vec<Trip> {
+        origins
+            .iter()
+            .enumerate()
+            .map(|(id, &origin)| {
+                Trip::new(id as i32, origin, Direction::Straight, VehicleKind::Car, 0)
+            })
+            .collect()
+    }
+
+    fn assert_near(value: Option<f64>, expected: f64) {
+        let value = value.expect("no measure");
+        assert!((value - expected).abs() < 0.01, "{} instead of {}", value, expected);
+    }
+
+    #[test]
+    fn head_on_ttc_and_drac() {
+        // Fronts 35 m apart closing in at 20 m/s
+        let vehicles = [car(0, (0.0, 0.0), 0.0, 10.0), car(1, (40.0, 0.0), 180.0, 10.0)];
+        assert_near(time_to_collision(&vehicles[0], &vehicles[1]), 1.75);
+
+        let mut monitor = SafetyMonitor::new(&Geometry::default(), SafetyThresholds::default());
+        let trips = trips(&[RoadDirection::East, RoadDirection::West]);
+        monitor.observe(&vehicles, &trips, &Clock::default());
+        let report = monitor.report();
+        assert_near(report.min_ttc, 1.75);
+        // 20 m/s to lose over the 17.5 m each can drive before the crash
+        assert_near(report.max_drac, 20.0 / 3.5);
+        assert_eq!(report.close_calls, 1);
+    }
+
+    #[test]
+    fn ttc_of_turning_vehicles_follows_their_rotated_bodies() {
+        let (sin, cos) = 45f64.to_radians().sin_cos();
+        // Side by side through a turn, one lane apart: their bounding boxes overlap, their
+        // bodies never do
+        let inner = car(0, (0.0, 0.0), 45.0, 8.0);
+        let outer = car(1, (-5.0 * sin, 5.0 * cos), 45.0, 8.0);
+        assert_eq!(time_to_collision(&inner, &outer), None);
+
+        // Closing in at 10 m/s on a car standing 15 m ahead on the way out of the turn
+        let follower = car(0, (0.0, 0.0), 45.0, 10.0);
+        let leader = car(1, (20.0 * cos, 20.0 * sin), 45.0, 0.0);
+        assert_near(time_to_collision(&follower, &leader), 1.5);
+
+        let mut monitor = SafetyMonitor::new(&Geometry::default(), SafetyThresholds::default());
+        let trips = trips(&[RoadDirection::North, RoadDirection::North]);
+        monitor.observe(&[follower, leader], &trips, &Clock::default());
+        assert_near(monitor.report().max_drac, 10.0 / 3.0);
+        assert_eq!(monitor.report().close_calls, 0);
+    }
+
+    #[test]
+    fn pet_between_vehicles_of_different_approaches() {
+        let geometry = Geometry::default();
+        let mut monitor = SafetyMonitor::new(&geometry, SafetyThresholds::default());
+        let mut clock = Clock::new(Duration::from_millis(100));
+        let tile = monitor.grid.center(2, 2);
+        let trips = trips(&[RoadDirection::West, RoadDirection::West, RoadDirection::North]);
+
+        // A car from West leaves the tile on tick 1, another one from West reaches it on
+        // tick 4 and leaves it on tick 5, then a car from North reaches it on tick 11
+        for tick in 0..12 {
+            clock.tick = tick;
+            let vehicles: Vec<Vehicle> = match tick {
+                0 => vec![car(0, tile, 180.0, 0.0)],
+                4 => vec![car(1, tile, 180.0, 0.0)],
+                11 => vec![car(2, tile, 90.0, 0.0)],
+                _ => vec![],
+            };
+            monitor.observe(&vehicles, &trips, &clock);
+        }
+        let report = monitor.report();
+        assert_eq!(report.pairs.len(), 1);
+        assert_eq!((report.pairs[0].first, report.pairs[0].second), (1, 2));
+        assert_near(report.pairs[0].min_pet, 0.6);
+        assert!(report.pairs[0].close_call);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{ fs, path::Path };
+use serde::Deserialize;
+
+use super::{
+    config::{ check_profile, Config, RawAcceleration, RawVelocities },
+    roads::{ Direction, Line, RoadDirection },
+    vehicle_type::{ VehicleKind, VehicleType },
+};
+
+/// Directory holding the library of named scenarios, one `<name>.toml` file each.
+pub const SCENARIO_DIR: &str = "scenarios";
+
+/// Vehicle spawned by a scenario.
+#[derive(Debug, Clone)]
+pub struct SpawnEvent {
+    /// Tick of the spawn, counted from the start of the playback.
+    pub tick: u64,
+    pub line: Line,
+    pub direction: Direction,
+    /// Type of the vehicle, with the velocities, accelerations and safe distance of the event.
+    pub vehicle_type: VehicleType,
+}
+
+/// Timed list of spawns played back by `VehiclesManagement::play`, usually loaded from a
+/// TOML file:
+///
+/// ```toml
+/// name = "Four simultaneous left turns"
+/// description = "One vehicle turns left from every approach at the same time."
+///
+/// [[events]]
+/// tick = 0
+/// road = "north"
+/// direction = "left"
+/// # Optional: lane index (default: the right-most lane permitting the manoeuvre), kind of
+/// # vehicle (default: car), velocities (km/h), accelerations (m/s²) and safe distance
+/// # (meters) (default: the ones of the kind in the configuration)
+/// lane = 2
+/// kind = "bus"
+/// velocities = { normal = 20 }
+/// acceleration = { max = 3.0 }
+/// safe_distance = 2.0
+/// ```
+///
+/// A spawn whose spawn point is taken is retried on the following ticks.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub description: String,
+    /// Spawns sorted by tick.
+    pub events: Vec<SpawnEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScenario {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    events: Vec<RawEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEvent {
+    tick: u64,
+    road: RoadDirection,
+    direction: Direction,
+    lane: Option<i32>,
+    kind: Option<VehicleKind>,
+    #[serde(default)]
+    velocities: RawVelocities,
+    #[serde(default)]
+    acceleration: RawAcceleration,
+    safe_distance: Option<f64>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path, config: &Config) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e|
+            format!("cannot read scenario file '{}': {}", path.display(), e)
+        )?;
+        Scenario::from_toml(&text, config).map_err(|e|
+            format!("invalid scenario file '{}': {}", path.display(), e)
+        )
+    }
+
+    /// Load the scenario at `name` if it is a file, otherwise `<name>.toml` from the library.
+    pub fn by_name(name: &str, config: &Config) -> Result<Self, String> {
+        let path = Path::new(name);
+        if path.is_file() {
+            return Scenario::load(path, config);
+        }
+        let path = Path::new(SCENARIO_DIR).join(format!("{}.toml", name));
+        if path.is_file() {
+            return Scenario::load(&path, config);
+        }
+        Err(
+            format!(
+                "unknown scenario '{}' (expected one of: {})",
+                name,
+                Scenario::library().join(", ")
+            )
+        )
+    }
+
+    /// Names of the scenarios of the library, sorted.
+    pub fn library() -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(SCENARIO_DIR)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Parse a scenario and resolve its events against the lanes and parameters of
+    /// `config`. Every invalid event is reported in the error.
+    pub fn from_toml(text: &str, config: &Config) -> Result<Self, String> {
+        let raw: RawScenario = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut errors = vec![];
+        let mut events = vec![];
+
+        for (i, event) in raw.events.iter().enumerate() {
+            let mut vehicle_type = config.vehicle_types
+                .get(event.kind.unwrap_or(VehicleKind::Car))
+                .clone();
+            vehicle_type.profile = event.velocities.profile(
+                vehicle_type.profile,
+                &event.acceleration,
+                event.safe_distance
+            );
+            check_profile(&vehicle_type.profile, &format!("events[{}].", i), &mut errors);
+
+            let lines = config.approaches
+                .iter()
+                .find(|lines| lines.first().is_some_and(|line| line.road_direction == event.road));
+            let Some(lines) = lines else {
+                errors.push(
+                    format!("`events[{}].road`: the layout has no {:?} approach", i, event.road)
+                );
+                continue;
+            };
+            let line = match event.lane {
+                Some(lane) =>
+                    match lines.iter().find(|line| line.lane == lane) {
+                        None => {
+                            errors.push(
+                                format!(
+                                    "`events[{}].lane`: the {:?} approach has no lane {}",
+                                    i,
+                                    event.road,
+                                    lane
+                                )
+                            );
+                            continue;
+                        }
+                        Some(line) if !line.directions.contains(&event.direction) => {
+                            errors.push(
+                                format!(
+                                    "`events[{}].direction`: lane {} of the {:?} approach does not permit {:?}",
+                                    i,
+                                    lane,
+                                    event.road,
+                                    event.direction
+                                )
+                            );
+                            continue;
+                        }
+                        Some(line) => line,
+                    }
+                None =>
+                    match lines.iter().find(|line| line.directions.contains(&event.direction)) {
+                        None => {
+                            errors.push(
+                                format!(
+                                    "`events[{}].direction`: no lane of the {:?} approach permits {:?}",
+                                    i,
+                                    event.road,
+                                    event.direction
+                                )
+                            );
+                            continue;
+                        }
+                        Some(line) => line,
+                    }
+            };
+            events.push(SpawnEvent {
+                tick: event.tick,
+                line: line.clone(),
+                direction: event.direction,
+                vehicle_type,
+            });
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        events.sort_by_key(|event| event.tick);
+        Ok(Scenario { name: raw.name, description: raw.description, events })
+    }
+}
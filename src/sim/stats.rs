@@ -0,0 +1,189 @@
+use std::{ fs, path::Path };
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    collision::Crash,
+    pedestrians::PedestrianReport,
+    roads::{ Direction, RoadDirection },
+    safety::SafetyReport,
+    trips::{ Trip, TripReport },
+    vehicle_type::VehicleKind,
+    vehicles_management::VehiclesManagement,
+};
+
+/// End-of-run statistics and the metadata of the run. Durations are in seconds, velocities
+/// in meters per second. Only the inputs of the run make it into the record, so the same
+/// inputs always export the same files.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub version: String,
+    pub seed: u64,
+    pub policy: String,
+    pub scenario: Option<String>,
+    pub config: Option<String>,
+    pub ticks: u64,
+    pub tick_duration: f64,
+    pub pixels_per_meter: f64,
+    pub simulated_time: f64,
+    pub number_of_vehicles: i32,
+    pub number_passed_intersection: i32,
+    pub max_velocity: f64,
+    pub min_velocity: f64,
+    pub max_time: f64,
+    pub min_time: f64,
+    pub crashes: usize,
+    pub trip_report: TripReport,
+    pub pedestrians: PedestrianReport,
+    /// Its pairs and conflict points are left out of the CSV row.
+    pub safety: SafetyReport,
+    /// Left out of the CSV row, exported to a file of its own.
+    pub trips: Vec<Trip>,
+    /// Left out of the CSV row.
+    pub crash_log: Vec<Crash>,
+}
+
+impl Stats {
+    /// Statistics of `vehicles` so far. `scenario` and `config` are left for the caller to fill.
+    pub fn new(vehicles: &VehiclesManagement) -> Self {
+        Stats {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: vehicles.seed,
+            policy: vehicles.policy.name().to_string(),
+            scenario: None,
+            config: None,
+            ticks: vehicles.clock.tick,
+            tick_duration: vehicles.clock.dt.as_secs_f64(),
+            pixels_per_meter: vehicles.geometry.pixels_per_meter,
+            simulated_time: vehicles.clock.now().as_secs_f64(),
+            number_of_vehicles: vehicles.number_of_vehicles,
+            number_passed_intersection: vehicles.number_passed_intersection,
+            max_velocity: vehicles.max_velocity,
+            min_velocity: vehicles.min_velocity,
+            max_time: vehicles.max_time.as_secs_f64(),
+            min_time: vehicles.min_time.as_secs_f64(),
+            crashes: vehicles.collisions.crashes.len(),
+            trip_report: vehicles.trip_report(),
+            pedestrians: vehicles.pedestrians.report(&vehicles.clock),
+            safety: vehicles.safety.report(),
+            trips: vehicles.trips.clone(),
+            crash_log: vehicles.collisions.crashes.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("stats are always serializable")
+    }
+
+    /// Header line and value line, with the keys of the JSON export (nested keys joined with
+    /// dots) but without the lists: trips, crashes, safety pairs and conflict points.
+    pub fn to_csv(&self) -> String {
+        let (header, values) = csv_row(self);
+        format!("{}\n{}\n", header, values)
+    }
+
+    /// Header line and one line per trip.
+    pub fn trips_to_csv(&self) -> String {
+        let (header, _) = csv_row(
+            &Trip::new(0, RoadDirection::North, Direction::Straight, VehicleKind::Car, 0)
+        );
+        let mut csv = format!("{}\n", header);
+        for trip in &self.trips {
+            csv += &csv_row(trip).1;
+            csv += "\n";
+        }
+        csv
+    }
+
+    /// Write the exports next to each other, `path` with a `.json`, a `.csv` and a
+    /// `.trips.csv` extension.
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        for (extension, content) in [
+            ("json", self.to_json()),
+            ("csv", self.to_csv()),
+            ("trips.csv", self.trips_to_csv()),
+        ] {
+            let file = path.with_extension(extension);
+            fs::write(&file, content).map_err(|e|
+                format!("cannot write stats file '{}': {}", file.display(), e)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Header and values of `value` serialized as a single CSV row. Arrays are left out.
+fn csv_row(value: &impl Serialize) -> (String, String) {
+    let mut fields = vec![];
+    flatten("", &serde_json::to_value(value).expect("serializable"), &mut fields);
+    let header: Vec<String> = fields
+        .iter()
+        .map(|(key, _)| csv_field(key))
+        .collect();
+    let values: Vec<String> = fields
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    (header.join(","), values.join(","))
+}
+
+fn flatten(key: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                let key = if key.is_empty() { name.clone() } else { format!("{}.{}", key, name) };
+                flatten(&key, value, fields);
+            }
+        }
+        Value::Array(_) => {}
+        Value::Null => fields.push((key.to_string(), String::new())),
+        Value::String(text) => fields.push((key.to_string(), csv_field(text))),
+        other => fields.push((key.to_string(), other.to_string())),
+    }
+}
+
+/// Quote `text` if it contains a separator, a quote or a line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::config::Config;
+
+    fn run(seed: u64) -> Stats {
+        let config = Config::default();
+        let mut vehicles = VehiclesManagement::with_config(seed, &config);
+        for tick in 0..2000 {
+            if tick % 10 == 0 {
+                vehicles.spawn_random(config.approaches.iter().collect());
+            }
+            vehicles.update();
+        }
+        Stats::new(&vehicles)
+    }
+
+    #[test]
+    fn same_seed_exports_the_same_stats() {
+        let stats = run(7);
+        assert!(stats.number_passed_intersection > 0);
+        assert_eq!(stats.to_json(), run(7).to_json());
+        assert_eq!(stats.to_csv(), run(7).to_csv());
+        assert_ne!(stats.to_json(), run(8).to_json());
+    }
+
+    #[test]
+    fn crossing_times_are_measured_in_ticks() {
+        let stats = run(7);
+        assert!(stats.min_time > 0.0 && stats.min_time <= stats.max_time);
+        for time in [stats.min_time, stats.max_time] {
+            let ticks = time / stats.tick_duration;
+            assert!((ticks - ticks.round()).abs() < 1e-6, "{}s is not a whole number of ticks", time);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use super::{
+    clock::Clock,
+    config::Config,
+    policy::IntersectionPolicy,
+    roads::{ Direction, Line, RoadDirection },
+    vehicle::Vehicle,
+    vehicle_type::VehicleKind,
+};
+
+/// Vehicle of `kind` entering the area of `config` on the default lane of `road_direction`
+/// for `direction`, the way `VehiclesManagement` spawns it.
+pub fn spawn(
+    id: i32,
+    config: &Config,
+    kind: VehicleKind,
+    road_direction: RoadDirection,
+    direction: Direction
+) -> Vehicle {
+    let line = Line::approach(road_direction)
+        .into_iter()
+        .find(|line| line.directions.contains(&direction))
+        .unwrap();
+    let vehicle_type = config.vehicle_types.get(kind);
+    let (x, y) = config.geometry.spawn_position(&line, vehicle_type.length);
+    let mut vehicle = Vehicle::new(id, road_direction, direction, x, y, vehicle_type);
+    vehicle.turn = config.geometry.turn_path(&line, direction, vehicle.wheelbase());
+    vehicle
+}
+
+/// Car entering on the default lane of `road_direction` for `direction`.
+pub fn car(
+    id: i32,
+    config: &Config,
+    road_direction: RoadDirection,
+    direction: Direction
+) -> Vehicle {
+    spawn(id, config, VehicleKind::Car, road_direction, direction)
+}
+
+/// Run `policy` on the layout of `config` with the vehicles of `arrivals`, each entering the
+/// area on its tick, until they all left it, calling `observe` with the clock and the
+/// vehicles after every tick.
+pub fn run(
+    policy: &mut dyn IntersectionPolicy,
+    config: &Config,
+    mut arrivals: Vec<(u64, Vehicle)>,
+    mut observe: impl FnMut(&Clock, &[Vehicle])
+) {
+    let stop_area = config.geometry.stop_area();
+    let mut clock = Clock::new(config.tick_duration);
+    let mut vehicles: Vec<Vehicle> = vec![];
+    while !arrivals.is_empty() || !vehicles.is_empty() {
+        assert!(clock.tick < 20_000, "vehicles still in the area at tick {}", clock.tick);
+        let (due, later) = arrivals.into_iter().partition(|(tick, _)| *tick <= clock.tick);
+        arrivals = later;
+        vehicles.extend(due.into_iter().map(|(_, vehicle): (u64, Vehicle)| vehicle));
+
+        let velocities = policy.velocities(&vehicles, &stop_area, &clock);
+        policy.hold_back(&[]);
+        for (vehicle, velocity) in vehicles.iter_mut().zip(velocities) {
+            vehicle.accelerate(velocity, clock.dt);
+            vehicle.step(clock.dt);
+        }
+        clock.advance();
+        observe(&clock, &vehicles);
+        vehicles.retain(|vehicle| config.geometry.is_on_screen(vehicle));
+    }
+}
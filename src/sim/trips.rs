@@ -0,0 +1,240 @@
+use std::{ collections::BTreeMap, time::Duration };
+use serde::Serialize;
+
+use super::{ clock::Clock, roads::{ Direction, RoadDirection }, vehicle_type::VehicleKind };
+
+/// Journey of one vehicle through the simulation. Ticks are `None` until the event happens.
+#[derive(Debug, Clone, Serialize)]
+pub struct Trip {
+    pub id: i32,
+    /// Approach the vehicle spawned on, or came from a neighbouring intersection by.
+    pub origin: RoadDirection,
+    /// Manoeuvre the vehicle takes at the intersection.
+    pub direction: Direction,
+    pub kind: VehicleKind,
+    pub spawn_tick: u64,
+    pub entry_tick: Option<u64>,
+    pub exit_tick: Option<u64>,
+    pub despawn_tick: Option<u64>,
+    /// Number of times the vehicle came to a halt.
+    pub stops: u32,
+    /// Number of ticks spent at a halt.
+    pub stopped_ticks: u64,
+    /// Number of ticks spent at a halt while held clear of the path of an emergency vehicle.
+    pub held_ticks: u64,
+    /// Ticks the vehicle would have taken to leave the intersection alone on the road.
+    pub free_flow_ticks: u64,
+}
+
+impl Trip {
+    pub fn new(
+        id: i32,
+        origin: RoadDirection,
+        direction: Direction,
+        kind: VehicleKind,
+        spawn_tick: u64
+    ) -> Self {
+        Trip {
+            id,
+            origin,
+            direction,
+            kind,
+            spawn_tick,
+            entry_tick: None,
+            exit_tick: None,
+            despawn_tick: None,
+            stops: 0,
+            stopped_ticks: 0,
+            held_ticks: 0,
+            free_flow_ticks: 0,
+        }
+    }
+
+    /// Ticks between entering and leaving the intersection.
+    pub fn crossing_ticks(&self) -> Option<u64> {
+        Some(self.exit_tick? - self.entry_tick?)
+    }
+
+    /// Ticks lost on the way to the intersection exit, compared with an empty road.
+    pub fn delay_ticks(&self) -> Option<u64> {
+        Some((self.exit_tick? - self.spawn_tick).saturating_sub(self.free_flow_ticks))
+    }
+
+    /// Ticks between spawning and leaving the screen.
+    pub fn travel_ticks(&self) -> Option<u64> {
+        Some(self.despawn_tick? - self.spawn_tick)
+    }
+}
+
+/// Distribution of a duration, in seconds. The statistics are `None` without samples.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimeSummary {
+    pub count: usize,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl TimeSummary {
+    pub fn new(samples: impl Iterator<Item = Duration>) -> Self {
+        let mut seconds: Vec<f64> = samples.map(|sample| sample.as_secs_f64()).collect();
+        if seconds.is_empty() {
+            return TimeSummary::default();
+        }
+        seconds.sort_by(f64::total_cmp);
+        let count = seconds.len();
+        // Nearest-rank percentile
+        let percentile = |p: f64| seconds[((p * (count as f64)).ceil() as usize).clamp(1, count) - 1];
+        TimeSummary {
+            count,
+            mean: Some(seconds.iter().sum::<f64>() / (count as f64)),
+            median: Some(percentile(0.5)),
+            p95: Some(percentile(0.95)),
+            p99: Some(percentile(0.99)),
+        }
+    }
+}
+
+/// Crossing time (inside the intersection) and travel time (spawn to despawn) of a set of trips.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TripSummary {
+    pub crossing_time: TimeSummary,
+    pub travel_time: TimeSummary,
+}
+
+impl TripSummary {
+    pub fn new<'a>(trips: impl Iterator<Item = &'a Trip> + Clone, clock: &Clock) -> Self {
+        TripSummary {
+            crossing_time: TimeSummary::new(
+                trips
+                    .clone()
+                    .filter_map(Trip::crossing_ticks)
+                    .map(|ticks| clock.duration(ticks))
+            ),
+            travel_time: TimeSummary::new(
+                trips.filter_map(Trip::travel_ticks).map(|ticks| clock.duration(ticks))
+            ),
+        }
+    }
+}
+
+/// Delays around the emergency vehicles of a run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EmergencyReport {
+    /// Delay of every emergency vehicle out of the intersection (see `Trip::delay_ticks`).
+    pub delay: TimeSummary,
+    /// Time each vehicle held for an emergency vehicle stood still while held.
+    pub imposed_delay: TimeSummary,
+    /// Sum of the imposed delays, in seconds.
+    pub total_imposed_delay: f64,
+}
+
+impl EmergencyReport {
+    pub fn new(trips: &[Trip], clock: &Clock) -> Self {
+        let held: Vec<u64> = trips
+            .iter()
+            .map(|trip| trip.held_ticks)
+            .filter(|&ticks| ticks > 0)
+            .collect();
+        EmergencyReport {
+            delay: TimeSummary::new(
+                trips
+                    .iter()
+                    .filter(|trip| trip.kind == VehicleKind::Emergency)
+                    .filter_map(Trip::delay_ticks)
+                    .map(|ticks| clock.duration(ticks))
+            ),
+            imposed_delay: TimeSummary::new(held.iter().map(|&ticks| clock.duration(ticks))),
+            total_imposed_delay: clock.duration(held.iter().sum()).as_secs_f64(),
+        }
+    }
+}
+
+/// Trip statistics of a run, overall and broken down by origin approach, by manoeuvre and
+/// by vehicle kind, and the delays around emergency vehicles.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TripReport {
+    pub all: TripSummary,
+    pub by_origin: BTreeMap<RoadDirection, TripSummary>,
+    pub by_direction: BTreeMap<Direction, TripSummary>,
+    pub by_kind: BTreeMap<VehicleKind, TripSummary>,
+    pub emergency: EmergencyReport,
+}
+
+impl TripReport {
+    pub fn new(trips: &[Trip], clock: &Clock) -> Self {
+        let mut report = TripReport {
+            all: TripSummary::new(trips.iter(), clock),
+            emergency: EmergencyReport::new(trips, clock),
+            ..TripReport::default()
+        };
+        for origin in [RoadDirection::North, RoadDirection::West, RoadDirection::South, RoadDirection::East] {
+            let summary = TripSummary::new(
+                trips.iter().filter(|trip| trip.origin == origin),
+                clock
+            );
+            report.by_origin.insert(origin, summary);
+        }
+        for direction in [Direction::Left, Direction::Straight, Direction::Right] {
+            let summary = TripSummary::new(
+                trips.iter().filter(|trip| trip.direction == direction),
+                clock
+            );
+            report.by_direction.insert(direction, summary);
+        }
+        for kind in VehicleKind::ALL {
+            let summary = TripSummary::new(
+                trips.iter().filter(|trip| trip.kind == kind),
+                clock
+            );
+            report.by_kind.insert(kind, summary);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(values: impl IntoIterator<Item = u64>) -> impl Iterator<Item = Duration> {
+        values.into_iter().map(Duration::from_secs)
+    }
+
+    #[test]
+    fn summary_without_samples() {
+        let summary = TimeSummary::new(seconds([]));
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.mean, None);
+        assert_eq!(summary.median, None);
+        assert_eq!(summary.p95, None);
+        assert_eq!(summary.p99, None);
+    }
+
+    #[test]
+    fn summary_of_one_sample() {
+        let summary = TimeSummary::new(seconds([3]));
+        assert_eq!(summary.count, 1);
+        assert_eq!(summary.mean, Some(3.0));
+        assert_eq!(summary.median, Some(3.0));
+        assert_eq!(summary.p95, Some(3.0));
+        assert_eq!(summary.p99, Some(3.0));
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        // In reverse order, to check the samples get sorted
+        let summary = TimeSummary::new(seconds((1..=100).rev()));
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.mean, Some(50.5));
+        assert_eq!(summary.median, Some(50.0));
+        assert_eq!(summary.p95, Some(95.0));
+        assert_eq!(summary.p99, Some(99.0));
+
+        let summary = TimeSummary::new(seconds([4, 1, 3, 2, 5]));
+        assert_eq!(summary.median, Some(3.0));
+        assert_eq!(summary.p95, Some(5.0));
+        assert_eq!(summary.p99, Some(5.0));
+    }
+}
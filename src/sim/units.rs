@@ -0,0 +1,12 @@
+/// Default scale of the window: a 50 pixel lane is 5 m wide.
+pub const DEFAULT_PIXELS_PER_METER: f64 = 10.0;
+
+/// Kilometers per hour in `meters_per_second`.
+pub fn kmh(meters_per_second: f64) -> f64 {
+    meters_per_second * 3.6
+}
+
+/// Meters per second in `kmh` kilometers per hour.
+pub fn meters_per_second(kmh: f64) -> f64 {
+    kmh / 3.6
+}
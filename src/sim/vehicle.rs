@@ -1,19 +1,105 @@
-use std::time::Instant;
+use std::time::Duration;
+use serde::{ Deserialize, Serialize };
 
 use super::{
+    car_following::CarFollowing,
+    geometry::TurnPath,
     roads::{ Direction, RoadDirection, RoadIntersection },
-    vehicles_management::{ NORMAL_VELOCITY, SAFE_DISTANCE },
+    vehicle_type::{ VehicleKind, VehicleType },
+    vehicles_management::{
+        COMFORTABLE_DECELERATION,
+        EMERGENCY_DECELERATION,
+        FAST_VELOCITY,
+        MAX_ACCELERATION,
+        NORMAL_VELOCITY,
+        SAFE_DISTANCE,
+        SLOW_VELOCITY,
+        TIME_HEADWAY,
+    },
 };
 
-#[derive(Debug, Clone)]
+/// Distance from each end of a vehicle to its front and rear axles, which both follow the
+/// turn paths, in meters.
+const AXLE_INSET: f64 = 1.0;
+
+/// Upper bound on the ticks simulated by `Vehicle::free_flow_ticks`.
+const MAX_FREE_FLOW_TICKS: u64 = 10_000;
+
+/// Distance to the intersection edge under which a vehicle braking for it stands at the
+/// stop line, in meters.
+const STOP_LINE_TOLERANCE: f64 = 0.05;
+
+/// Velocity across the road of a vehicle changing lane, in meters per second.
+pub const LANE_CHANGE_VELOCITY: f64 = 2.5;
+
+/// Velocities (meters per second) a vehicle drives at, the gap it keeps to others (meters),
+/// and how hard it speeds up and brakes (meters per second squared).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DrivingProfile {
+    pub slow: f64,
+    pub normal: f64,
+    pub fast: f64,
+    pub safe_distance: f64,
+    pub max_acceleration: f64,
+    /// Deceleration used to stop at a stop line or behind another vehicle.
+    pub comfortable_deceleration: f64,
+    /// Hardest deceleration the vehicle is capable of.
+    pub emergency_deceleration: f64,
+    /// Model followed on the approach lanes.
+    pub car_following: CarFollowing,
+    /// Time gap kept to the vehicle ahead by the car-following model, in seconds.
+    pub time_headway: f64,
+}
+
+impl Default for DrivingProfile {
+    fn default() -> Self {
+        DrivingProfile {
+            slow: SLOW_VELOCITY,
+            normal: NORMAL_VELOCITY,
+            fast: FAST_VELOCITY,
+            safe_distance: SAFE_DISTANCE,
+            max_acceleration: MAX_ACCELERATION,
+            comfortable_deceleration: COMFORTABLE_DECELERATION,
+            emergency_deceleration: EMERGENCY_DECELERATION,
+            car_following: CarFollowing::default(),
+            time_headway: TIME_HEADWAY,
+        }
+    }
+}
+
+impl DrivingProfile {
+    /// Distance needed to stop from `velocity` at the comfortable deceleration.
+    pub fn braking_distance(&self, velocity: f64) -> f64 {
+        (velocity * velocity) / (2.0 * self.comfortable_deceleration)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Vehicle {
     pub id: i32,
     pub road_direction: RoadDirection,
     pub direction: Direction,
-    pub x: i32,
-    pub y: i32,
-    pub velocity: i32,
-    pub intersection_entry_time: Option<Instant>,
+    pub kind: VehicleKind,
+    /// Length of the body along the heading, in meters.
+    pub length: f64,
+    /// Width of the body across the heading, in meters.
+    pub width: f64,
+    /// Center of the body, in meters.
+    pub x: f64,
+    pub y: f64,
+    /// Direction of travel, in degrees (see `RoadDirection::heading`).
+    pub heading: f64,
+    /// Arc to follow through the intersection, until the turn is completed.
+    pub turn: Option<TurnPath>,
+    /// Meters driven along `turn` since it started.
+    pub turn_progress: Option<f64>,
+    /// Position across the road of the center of the lane the vehicle moves to, while it
+    /// changes lane.
+    pub lane_change: Option<f64>,
+    /// Meters per second.
+    pub velocity: f64,
+    pub profile: DrivingProfile,
+    pub intersection_entry_tick: Option<u64>,
     pub has_stop: bool,
 }
 
@@ -22,69 +108,269 @@ impl Vehicle {
         id: i32,
         road_direction: RoadDirection,
         direction: Direction,
-        x: i32,
-        y: i32
+        x: f64,
+        y: f64,
+        vehicle_type: &VehicleType
     ) -> Self {
+        let profile = vehicle_type.profile;
         Vehicle {
             id,
             road_direction,
             direction,
+            kind: vehicle_type.kind,
+            length: vehicle_type.length,
+            width: vehicle_type.width,
             x,
             y,
-            velocity: NORMAL_VELOCITY,
-            intersection_entry_time: None,
+            heading: road_direction.heading(),
+            turn: None,
+            turn_progress: None,
+            lane_change: None,
+            velocity: profile.normal,
+            profile,
+            intersection_entry_tick: None,
             has_stop: false,
         }
     }
 
-    pub fn will_collide(&self, other_vehicle: &Vehicle) -> bool {
-        let (next_x, next_y) = match self.road_direction {
-            RoadDirection::North => (self.x, self.y - self.velocity),
-            RoadDirection::South => (self.x, self.y + self.velocity),
-            RoadDirection::West => (self.x - self.velocity, self.y),
-            RoadDirection::East => (self.x + self.velocity, self.y),
+    /// Unit vectors along and across the heading of the vehicle, and the half extents of
+    /// its body along them.
+    fn axes(&self) -> [((f64, f64), f64); 2] {
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+        [
+            ((cos, sin), self.length / 2.0),
+            ((-sin, cos), self.width / 2.0),
+        ]
+    }
+
+    /// Corners of the body, rotated with the heading of the vehicle.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let (cx, cy) = (self.x, self.y);
+        let [((ux, uy), length), ((vx, vy), width)] = self.axes();
+        [(1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0)].map(|(u, v)| {
+            (cx + u * length * ux + v * width * vx, cy + u * length * uy + v * width * vy)
+        })
+    }
+
+    /// Smallest axis-aligned box (left, top, right, bottom) holding the body.
+    pub fn bounding_box(&self) -> (f64, f64, f64, f64) {
+        let corners = self.corners();
+        let min = |coordinate: fn(&(f64, f64)) -> f64| {
+            corners.iter().map(coordinate).fold(f64::INFINITY, f64::min)
         };
+        let max = |coordinate: fn(&(f64, f64)) -> f64| {
+            corners.iter().map(coordinate).fold(f64::NEG_INFINITY, f64::max)
+        };
+        (
+            min(|corner| corner.0),
+            min(|corner| corner.1),
+            max(|corner| corner.0),
+            max(|corner| corner.1),
+        )
+    }
 
-        let y_diff = (next_y - other_vehicle.y).abs();
-        let x_diff = (next_x - other_vehicle.x).abs();
+    /// Whether the bodies of both vehicles, rotated with their heading, overlap.
+    pub fn overlaps(&self, other: &Vehicle) -> bool {
+        let (dx, dy) = (other.x - self.x, other.y - self.y);
+        let (self_axes, other_axes) = (self.axes(), other.axes());
+        // Separating axis test on the sides of both footprints
+        self_axes
+            .iter()
+            .chain(other_axes.iter())
+            .all(|((nx, ny), _)| {
+                let extent = |axes: &[((f64, f64), f64); 2]| {
+                    axes.iter()
+                        .map(|((ux, uy), half)| half * (ux * nx + uy * ny).abs())
+                        .sum::<f64>()
+                };
+                (dx * nx + dy * ny).abs() < extent(&self_axes) + extent(&other_axes) - 1e-6
+            })
+    }
 
-        match self.road_direction {
-            RoadDirection::North | RoadDirection::South => {
-                y_diff < 48 + SAFE_DISTANCE && x_diff < 40
+    /// Distance from the body, rotated with the heading of the vehicle, to the point
+    /// (`x`, `y`); zero inside the body.
+    pub fn distance_to_point(&self, x: f64, y: f64) -> f64 {
+        let (dx, dy) = (x - self.x, y - self.y);
+        let [along, across] = self.axes().map(|((ux, uy), half)| {
+            ((dx * ux + dy * uy).abs() - half).max(0.0)
+        });
+        along.hypot(across)
+    }
+
+    /// Velocity (x, y) along the heading, in meters per second.
+    pub fn velocity_vector(&self) -> (f64, f64) {
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+        (self.velocity * cos, self.velocity * sin)
+    }
+
+    /// Change the velocity towards `target` for a tick of `dt`, as fast as the maximum
+    /// acceleration or the emergency deceleration allow.
+    pub fn accelerate(&mut self, target: f64, dt: Duration) {
+        let dt = dt.as_secs_f64();
+        let lowest = (self.velocity - self.profile.emergency_deceleration * dt).max(0.0);
+        let highest = self.velocity + self.profile.max_acceleration * dt;
+        self.velocity = target.max(0.0).clamp(lowest, highest);
+    }
+
+    /// Distance covered before standing still when braking at `deceleration` from the
+    /// current velocity.
+    pub fn stopping_distance(&self, deceleration: f64) -> f64 {
+        (self.velocity * self.velocity) / (2.0 * deceleration)
+    }
+
+    /// Highest velocity for a tick of `dt` after which the vehicle can still stop within
+    /// `distance` meters braking at its comfortable deceleration.
+    pub fn velocity_to_stop_within(&self, distance: f64, dt: Duration) -> f64 {
+        if distance < STOP_LINE_TOLERANCE {
+            return 0.0;
+        }
+        // Solve v·dt + v² / 2b = distance
+        let dt = dt.as_secs_f64();
+        let deceleration = self.profile.comfortable_deceleration;
+        deceleration * ((dt * dt + (2.0 * distance) / deceleration).sqrt() - dt)
+    }
+
+    /// Move for a tick of `dt` at the current velocity, along the turn path once its start
+    /// is reached, and towards the lane it changes to at `LANE_CHANGE_VELOCITY`.
+    pub fn step(&mut self, dt: Duration) {
+        if let Some(target) = self.lane_change {
+            let shift = LANE_CHANGE_VELOCITY * dt.as_secs_f64();
+            let across = match self.road_direction {
+                RoadDirection::North | RoadDirection::South => &mut self.x,
+                RoadDirection::West | RoadDirection::East => &mut self.y,
+            };
+            *across += (target - *across).clamp(-shift, shift);
+            if *across == target {
+                self.lane_change = None;
             }
-            RoadDirection::East | RoadDirection::West => {
-                x_diff < 48 + SAFE_DISTANCE && y_diff < 40
+        }
+        let distance = self.velocity * dt.as_secs_f64();
+        let Some(turn) = self.turn else {
+            self.advance(distance);
+            return;
+        };
+        // Progress of the front axle from the start of the turn
+        let reach = self.length / 2.0 - self.axle_inset();
+        let progress = match self.turn_progress {
+            Some(progress) => progress + distance,
+            None => {
+                let (start_x, start_y) = turn.start;
+                let to_start = match self.road_direction {
+                    RoadDirection::North => start_y - (self.y + reach),
+                    RoadDirection::South => self.y - reach - start_y,
+                    RoadDirection::West => self.x - reach - start_x,
+                    RoadDirection::East => start_x - (self.x + reach),
+                };
+                if distance < to_start {
+                    self.advance(distance);
+                    return;
+                }
+                distance - to_start
             }
+        };
+
+        // The body lies between its axles, the rear one cutting the corner behind the front one
+        let wheelbase = self.wheelbase();
+        let (front_x, front_y) = turn.point_at(progress);
+        let (rear_x, rear_y) = turn.point_at(progress - wheelbase);
+        (self.x, self.y) = ((front_x + rear_x) / 2.0, (front_y + rear_y) / 2.0);
+        if progress < turn.length() + wheelbase {
+            self.heading = if wheelbase > 0.0 {
+                let chord = (front_y - rear_y).atan2(front_x - rear_x).to_degrees();
+                turn.heading + (chord - turn.heading + 180.0).rem_euclid(360.0) - 180.0
+            } else {
+                turn.heading_at(progress.clamp(0.0, turn.length()))
+            };
+            self.turn_progress = Some(progress);
+            return;
         }
+        // Turn completed, go on along the lane turned into
+        self.road_direction = self.road_direction.after_turn(self.direction);
+        self.direction = Direction::Straight;
+        self.heading = self.road_direction.heading();
+        self.turn = None;
+        self.turn_progress = None;
     }
 
-    pub fn has_to_slow(&self, other_vehicle: &Vehicle) -> bool {
-        let (next_x, next_y) = match self.road_direction {
-            RoadDirection::North => (self.x, self.y),
-            RoadDirection::South => (self.x, self.y),
-            RoadDirection::West => (self.x, self.y),
-            RoadDirection::East => (self.x, self.y),
-        };
+    /// Distance from each end of the body to its axles, shorter for vehicles too short to
+    /// fit two axles apart.
+    fn axle_inset(&self) -> f64 {
+        AXLE_INSET.min(self.length / 2.0)
+    }
 
-        let y_diff = (next_y - other_vehicle.y).abs();
-        let x_diff = (next_x - other_vehicle.x).abs();
+    /// Distance between the axles, in meters.
+    pub fn wheelbase(&self) -> f64 {
+        self.length - self.axle_inset() * 2.0
+    }
 
+    /// Move `distance` meters straight ahead.
+    fn advance(&mut self, distance: f64) {
         match self.road_direction {
             RoadDirection::North => {
-                y_diff < 0 && y_diff.abs() < 48 + SAFE_DISTANCE * 3 && x_diff.abs() < 40
+                self.y += distance;
             }
             RoadDirection::South => {
-                y_diff > 0 && y_diff.abs() < 48 + SAFE_DISTANCE * 3 && x_diff.abs() < 40
+                self.y -= distance;
             }
             RoadDirection::West => {
-                x_diff < 0 && x_diff.abs() < 48 + SAFE_DISTANCE * 3 && y_diff.abs() < 40
+                self.x -= distance;
             }
             RoadDirection::East => {
-                x_diff > 0 && x_diff.abs() < 48 + SAFE_DISTANCE * 3 && y_diff.abs() < 40
+                self.x += distance;
             }
         }
     }
 
+    /// Distance between the front of the vehicle and the nearest corner of `other` if
+    /// `other` is ahead, their bodies overlapping across the heading. The rear of a long
+    /// vehicle turning out of the lane is still ahead as long as it sticks into the lane.
+    pub fn gap_to(&self, other: &Vehicle) -> Option<f64> {
+        if other.id == self.id {
+            return None;
+        }
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+        let along = |(x, y): (f64, f64)| (x - self.x) * cos + (y - self.y) * sin;
+        let across = |(x, y): (f64, f64)| (y - self.y) * cos - (x - self.x) * sin;
+        if along((other.x, other.y)) <= 0.0 {
+            return None;
+        }
+        let corners = other.corners();
+        let half_width = self.width / 2.0;
+        let (left, right) = corners
+            .iter()
+            .map(|&corner| across(corner))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), across| {
+                (min.min(across), max.max(across))
+            });
+        if left >= half_width || right <= -half_width {
+            return None;
+        }
+        let nearest = corners
+            .iter()
+            .map(|&corner| along(corner))
+            .fold(f64::INFINITY, f64::min);
+        Some(nearest - self.length / 2.0)
+    }
+
+    /// Gap to the nearest vehicle ahead (see `gap_to`), `None` if the road ahead is clear.
+    pub fn gap_ahead(&self, vehicles: &[Vehicle]) -> Option<f64> {
+        self.leader(vehicles).map(|(gap, _)| gap)
+    }
+
+    /// Whether `other` heads the same way as the vehicle, within 45 degrees.
+    pub fn heads_same_way(&self, other: &Vehicle) -> bool {
+        ((other.heading - self.heading).rem_euclid(360.0) - 180.0).abs() > 135.0
+    }
+
+    /// Nearest vehicle ahead and the gap to it.
+    pub fn leader<'a>(&self, vehicles: &'a [Vehicle]) -> Option<(f64, &'a Vehicle)> {
+        vehicles
+            .iter()
+            .filter_map(|other| Some((self.gap_to(other)?, other)))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+    }
+
+    /// Whether the center of the vehicle is inside the intersection.
     pub fn is_in_intersection(&self, intersection: &RoadIntersection) -> bool {
         if
             self.x >= intersection.x &&
@@ -97,73 +383,253 @@ impl Vehicle {
         false
     }
 
-    pub fn is_ahead_clear(&self, vehicles: &[Vehicle]) -> bool {
-        for vehicle in vehicles {
-            if vehicle.id == self.id {
-                continue;
+    /// Ticks of `dt` the vehicle would take to leave the intersection (its center, as in
+    /// `is_in_intersection`) alone on the road, speeding up to its fast velocity.
+    pub fn free_flow_ticks(&self, intersection: &RoadIntersection, dt: Duration) -> u64 {
+        let mut ghost = self.clone();
+        let mut entered = false;
+        for tick in 1..=MAX_FREE_FLOW_TICKS {
+            ghost.accelerate(ghost.profile.fast, dt);
+            ghost.step(dt);
+            let inside = ghost.is_in_intersection(intersection);
+            if entered && !inside {
+                return tick;
             }
+            entered |= inside;
+        }
+        MAX_FREE_FLOW_TICKS
+    }
 
-            let x_diff = (self.x - vehicle.x).abs();
-            let y_diff = (self.y - vehicle.y).abs();
+    /// Whether the vehicle, currently outside the intersection, would enter it by moving
+    /// at `velocity` for a tick of `dt`.
+    pub fn is_entering(&self, intersection: &RoadIntersection, velocity: f64, dt: Duration) -> bool {
+        if intersection.overlaps(self) {
+            return false;
+        }
+        let mut next = self.clone();
+        next.velocity = velocity;
+        next.step(dt);
+        intersection.overlaps(&next)
+    }
 
-            match self.road_direction {
-                RoadDirection::North => {
-                    if vehicle.y > self.y && x_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::South => {
-                    if vehicle.y < self.y && x_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::West => {
-                    if vehicle.x < self.x && y_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::East => {
-                    if vehicle.x > self.x && y_diff < 40 {
-                        return false;
-                    }
-                }
-            }
+    /// Whether the vehicle has to brake now to stop before the intersection, or stands at
+    /// its edge: aiming for `velocity` this tick would leave less than the comfortable
+    /// stopping distance.
+    pub fn is_at_stop_line(
+        &self,
+        intersection: &RoadIntersection,
+        velocity: f64,
+        dt: Duration
+    ) -> bool {
+        let Some(distance) = self.distance_to_intersection(intersection) else {
+            return false;
+        };
+        let mut next = self.clone();
+        next.accelerate(velocity, dt);
+        let left = distance - next.velocity * dt.as_secs_f64();
+        left < next.stopping_distance(self.profile.comfortable_deceleration) + STOP_LINE_TOLERANCE
+    }
+
+    /// Position of the center of the vehicle across its road.
+    pub fn across(&self) -> f64 {
+        match self.road_direction {
+            RoadDirection::North | RoadDirection::South => self.x,
+            RoadDirection::West | RoadDirection::East => self.y,
         }
-        true
     }
 
-    pub fn is_front_clear(&self, vehicles: &[Vehicle]) -> bool {
-        for vehicle in vehicles {
-            if vehicle.id == self.id {
-                continue;
-            }
+    /// The vehicle moved across its road to `across`.
+    pub fn moved_across(&self, across: f64) -> Vehicle {
+        match self.road_direction {
+            RoadDirection::North | RoadDirection::South => Vehicle { x: across, ..self.clone() },
+            RoadDirection::West | RoadDirection::East => Vehicle { y: across, ..self.clone() },
+        }
+    }
 
-            let x_diff = (self.x - vehicle.x).abs();
-            let y_diff = (self.y - vehicle.y).abs();
+    /// Whether the vehicle, braking at its comfortable deceleration if it has to, would be
+    /// through a lane change to the lane centered at `across` before it reaches
+    /// `intersection`.
+    pub fn can_change_lane(&self, across: f64, intersection: &RoadIntersection) -> bool {
+        let Some(distance) = self.distance_to_intersection(intersection) else {
+            return false;
+        };
+        let time = (across - self.across()).abs() / LANE_CHANGE_VELOCITY;
+        let deceleration = self.profile.comfortable_deceleration;
+        let shortest = if self.velocity > deceleration * time {
+            self.velocity * time - (deceleration * time * time) / 2.0
+        } else {
+            self.profile.braking_distance(self.velocity)
+        };
+        distance >= shortest
+    }
 
-            match self.road_direction {
-                RoadDirection::North => {
-                    if vehicle.y > self.y && y_diff < 48 * 2 && x_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::South => {
-                    if vehicle.y < self.y && y_diff < 48 * 2 && x_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::West => {
-                    if vehicle.x < self.x && x_diff < 48 * 2 && y_diff < 40 {
-                        return false;
-                    }
-                }
-                RoadDirection::East => {
-                    if vehicle.x > self.x && x_diff < 48 * 2 && y_diff < 40 {
-                        return false;
-                    }
-                }
-            }
+    /// Whether the vehicle stands still at the stop line.
+    pub fn is_stopped_at(&self, intersection: &RoadIntersection) -> bool {
+        self.velocity == 0.0 &&
+            self
+                .distance_to_intersection(intersection)
+                .is_some_and(|distance| distance < STOP_LINE_TOLERANCE)
+    }
+
+    /// Highest velocity for a tick of `dt` that still lets the vehicle stop comfortably at
+    /// the intersection edge.
+    pub fn velocity_to_stop_at(&self, intersection: &RoadIntersection, dt: Duration) -> f64 {
+        self.velocity_to_stop_within(
+            self.distance_to_intersection(intersection).unwrap_or(0.0),
+            dt
+        )
+    }
+
+    /// Highest velocity for a tick of `dt` that still lets the vehicle stop comfortably a
+    /// safe distance behind the nearest of `vehicles` ahead, unbounded on a clear road.
+    pub fn velocity_to_follow(&self, vehicles: &[Vehicle], dt: Duration) -> f64 {
+        match self.gap_ahead(vehicles) {
+            Some(gap) => self.velocity_to_stop_within(gap - self.profile.safe_distance, dt),
+            None => f64::INFINITY,
         }
-        true
+    }
+
+    /// Distance in meters between the front of the vehicle and the intersection edge, or
+    /// `None` once the vehicle reached the intersection or is driving away from it.
+    pub fn distance_to_intersection(&self, intersection: &RoadIntersection) -> Option<f64> {
+        let (left, top) = (intersection.x, intersection.y);
+        let right = left + intersection.width;
+        let bottom = top + intersection.height;
+        let half = self.length / 2.0;
+        let distance = match self.road_direction {
+            RoadDirection::North => top - (self.y + half),
+            RoadDirection::South => self.y - half - bottom,
+            RoadDirection::West => self.x - half - right,
+            RoadDirection::East => left - (self.x + half),
+        };
+        if distance >= 0.0 { Some(distance) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ clock::Clock, config::Config, testing::car };
+
+    #[test]
+    fn velocity_changes_within_the_limits_of_the_profile() {
+        let config = Config::default();
+        let dt = Duration::from_millis(100);
+        let mut vehicle = car(0, &config, RoadDirection::North, Direction::Straight);
+        vehicle.velocity = 10.0;
+        vehicle.accelerate(100.0, dt);
+        assert!((vehicle.velocity - (10.0 + MAX_ACCELERATION * 0.1)).abs() < 1e-9);
+        vehicle.accelerate(0.0, dt);
+        assert!((vehicle.velocity - (10.25 - EMERGENCY_DECELERATION * 0.1)).abs() < 1e-9);
+        vehicle.accelerate(9.5, dt);
+        assert_eq!(vehicle.velocity, 9.5);
+        vehicle.velocity = 0.5;
+        vehicle.accelerate(-1.0, dt);
+        assert_eq!(vehicle.velocity, 0.0);
+    }
+
+    #[test]
+    fn velocity_to_stop_within_leaves_the_room_to_stop() {
+        let config = Config::default();
+        let dt = Duration::from_millis(16);
+        let vehicle = car(0, &config, RoadDirection::North, Direction::Straight);
+        for distance in [0.5, 5.0, 20.0, 80.0] {
+            let velocity = vehicle.velocity_to_stop_within(distance, dt);
+            let needed = velocity * dt.as_secs_f64() + vehicle.profile.braking_distance(velocity);
+            assert!((needed - distance).abs() < 1e-9, "{} m to stop within {} m", needed, distance);
+        }
+        assert_eq!(vehicle.velocity_to_stop_within(STOP_LINE_TOLERANCE / 2.0, dt), 0.0);
+    }
+
+    #[test]
+    fn vehicle_brakes_comfortably_to_the_stop_line() {
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let clock = Clock::new(config.tick_duration);
+        let dt = clock.dt.as_secs_f64();
+        let mut vehicle = car(0, &config, RoadDirection::West, Direction::Straight);
+        for _ in 0..1000 {
+            let distance = vehicle.distance_to_intersection(&stop_area).unwrap();
+            let velocity = vehicle.profile.normal.min(
+                vehicle.velocity_to_stop_at(&stop_area, clock.dt)
+            );
+            let previous = vehicle.velocity;
+            vehicle.accelerate(velocity, clock.dt);
+            vehicle.step(clock.dt);
+            // Only the last centimeters before the stop line are covered braking harder
+            assert!(
+                distance < STOP_LINE_TOLERANCE ||
+                    previous - vehicle.velocity <= COMFORTABLE_DECELERATION * dt + 1e-9,
+                "braked from {} to {} m/s {} m before the stop line",
+                previous,
+                vehicle.velocity,
+                distance
+            );
+            assert!(vehicle.distance_to_intersection(&stop_area).is_some(), "ran the stop line");
+        }
+        assert!(vehicle.is_stopped_at(&stop_area));
+    }
+
+    #[test]
+    fn follower_stops_a_safe_distance_behind_the_vehicle_ahead() {
+        let config = Config::default();
+        let clock = Clock::new(config.tick_duration);
+        let mut leader = car(0, &config, RoadDirection::East, Direction::Straight);
+        leader.x += 20.0;
+        leader.velocity = 0.0;
+        let mut follower = car(1, &config, RoadDirection::East, Direction::Straight);
+        assert!((follower.gap_to(&leader).unwrap() - 15.0).abs() < 1e-9);
+        for _ in 0..1000 {
+            let velocity = follower.profile.normal.min(
+                follower.velocity_to_follow(std::slice::from_ref(&leader), clock.dt)
+            );
+            follower.accelerate(velocity, clock.dt);
+            follower.step(clock.dt);
+            assert!(!follower.overlaps(&leader));
+        }
+        assert_eq!(follower.velocity, 0.0);
+        let gap = follower.gap_to(&leader).unwrap();
+        assert!((gap - SAFE_DISTANCE).abs() < STOP_LINE_TOLERANCE, "stopped {} m behind", gap);
+    }
+
+    #[test]
+    fn vehicle_changes_lane_gradually() {
+        let config = Config::default();
+        let clock = Clock::new(config.tick_duration);
+        let dt = clock.dt.as_secs_f64();
+        let lane = config.geometry.to_meters(config.geometry.lane_width);
+        let mut vehicle = car(0, &config, RoadDirection::West, Direction::Straight);
+        let target = vehicle.y + lane;
+        vehicle.lane_change = Some(target);
+        let mut ticks = 0;
+        while vehicle.lane_change.is_some() {
+            let y = vehicle.y;
+            vehicle.step(clock.dt);
+            assert!(vehicle.y > y && vehicle.y - y <= LANE_CHANGE_VELOCITY * dt + 1e-9);
+            ticks += 1;
+        }
+        assert_eq!(vehicle.y, target);
+        let time = (ticks as f64) * dt;
+        let expected = lane / LANE_CHANGE_VELOCITY;
+        assert!((time - expected).abs() <= dt + 1e-9, "changed lane in {} s", time);
+    }
+
+    #[test]
+    fn lane_change_is_through_before_the_stop_line() {
+        let config = Config::default();
+        let stop_area = config.geometry.stop_area();
+        let lane = config.geometry.to_meters(config.geometry.lane_width);
+        let mut vehicle = car(0, &config, RoadDirection::West, Direction::Straight);
+        let one_lane = vehicle.y + lane;
+        let two_lanes = vehicle.y + 2.0 * lane;
+        vehicle.x = stop_area.x + stop_area.width + 20.0 + vehicle.length / 2.0;
+        vehicle.velocity = vehicle.profile.fast;
+        assert!(vehicle.can_change_lane(one_lane, &stop_area));
+        assert!(!vehicle.can_change_lane(two_lanes, &stop_area));
+        // Slower, it brakes to the stop line as it moves over
+        vehicle.velocity = vehicle.profile.normal;
+        assert!(vehicle.can_change_lane(two_lanes, &stop_area));
+        vehicle.x -= 15.0;
+        assert!(!vehicle.can_change_lane(one_lane, &stop_area));
     }
 }
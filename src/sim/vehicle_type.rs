@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use rand::Rng;
+use serde::{ Deserialize, Serialize };
+
+use super::{ units::meters_per_second, vehicle::DrivingProfile };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VehicleKind {
+    Car,
+    Motorbike,
+    Bus,
+    Truck,
+    /// Ambulance, given the right of way at the intersection (see `EmergencyPreemption`).
+    Emergency,
+}
+
+impl VehicleKind {
+    pub const ALL: [VehicleKind; 5] = [
+        VehicleKind::Car,
+        VehicleKind::Motorbike,
+        VehicleKind::Bus,
+        VehicleKind::Truck,
+        VehicleKind::Emergency,
+    ];
+
+    /// Name of the kind in configuration and scenario files.
+    pub fn name(self) -> &'static str {
+        match self {
+            VehicleKind::Car => "car",
+            VehicleKind::Motorbike => "motorbike",
+            VehicleKind::Bus => "bus",
+            VehicleKind::Truck => "truck",
+            VehicleKind::Emergency => "emergency",
+        }
+    }
+}
+
+/// Dimensions, speed range, accelerations and look of one kind of vehicle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleType {
+    pub kind: VehicleKind,
+    /// Length of the body along its heading, in meters.
+    pub length: f64,
+    /// Width of the body across its heading, in meters.
+    pub width: f64,
+    pub profile: DrivingProfile,
+    /// Sprites drawn for the type: `assets/sprites/<sprite>_<north|west|south|est>.png`.
+    pub sprite: String,
+}
+
+impl VehicleType {
+    /// Built-in type of `kind`, drawn with the sprites named after it. Cars drive with `car`;
+    /// the other kinds keep its safe distance, car-following model and time headway, with
+    /// velocities and accelerations of their own.
+    pub fn builtin(kind: VehicleKind, car: &DrivingProfile) -> Self {
+        let (length, width, [slow, normal, fast], [max, comfortable, emergency]) = match kind {
+            VehicleKind::Car => {
+                return VehicleType {
+                    kind,
+                    length: 5.0,
+                    width: 4.0,
+                    profile: *car,
+                    sprite: kind.name().to_string(),
+                };
+            }
+            VehicleKind::Motorbike => (2.5, 1.5, [15.0, 35.0, 50.0], [4.0, 3.5, 8.0]),
+            VehicleKind::Bus => (12.0, 4.5, [10.0, 25.0, 35.0], [1.2, 2.5, 6.0]),
+            VehicleKind::Truck => (10.0, 4.5, [10.0, 25.0, 40.0], [1.0, 2.5, 6.0]),
+            VehicleKind::Emergency => (6.0, 4.0, [20.0, 40.0, 55.0], [3.0, 3.5, 8.0]),
+        };
+        VehicleType {
+            kind,
+            length,
+            width,
+            profile: DrivingProfile {
+                slow: meters_per_second(slow),
+                normal: meters_per_second(normal),
+                fast: meters_per_second(fast),
+                max_acceleration: max,
+                comfortable_deceleration: comfortable,
+                emergency_deceleration: emergency,
+                ..*car
+            },
+            sprite: kind.name().to_string(),
+        }
+    }
+}
+
+/// Every vehicle type, one per kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleCatalog {
+    types: BTreeMap<VehicleKind, VehicleType>,
+}
+
+impl VehicleCatalog {
+    /// The built-in types, cars driving with `car`.
+    pub fn new(car: &DrivingProfile) -> Self {
+        VehicleCatalog {
+            types: VehicleKind::ALL
+                .into_iter()
+                .map(|kind| (kind, VehicleType::builtin(kind, car)))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, kind: VehicleKind) -> &VehicleType {
+        &self.types[&kind]
+    }
+
+    pub fn get_mut(&mut self, kind: VehicleKind) -> &mut VehicleType {
+        self.types.get_mut(&kind).expect("the catalog holds every kind")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &VehicleType> {
+        self.types.values()
+    }
+}
+
+impl Default for VehicleCatalog {
+    fn default() -> Self {
+        Self::new(&DrivingProfile::default())
+    }
+}
+
+/// Relative frequencies of the kinds of the vehicles spawned at random. Emergency vehicles
+/// are left out of the default mix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrafficMix {
+    /// Kinds with a positive weight.
+    weights: Vec<(VehicleKind, f64)>,
+}
+
+impl TrafficMix {
+    /// Mix drawing each kind in proportion to its weight. Kinds without a positive weight are
+    /// never drawn; without any, only cars are.
+    pub fn new(weights: impl IntoIterator<Item = (VehicleKind, f64)>) -> Self {
+        let weights: Vec<(VehicleKind, f64)> = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        if weights.is_empty() {
+            return TrafficMix { weights: vec![(VehicleKind::Car, 1.0)] };
+        }
+        TrafficMix { weights }
+    }
+
+    pub fn pick(&self, rng: &mut impl Rng) -> VehicleKind {
+        if self.weights.len() == 1 {
+            return self.weights[0].0;
+        }
+        let total: f64 = self.weights
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum();
+        let mut draw = rng.gen_range(0.0..total);
+        for &(kind, weight) in &self.weights {
+            if draw < weight {
+                return kind;
+            }
+            draw -= weight;
+        }
+        self.weights[self.weights.len() - 1].0
+    }
+}
+
+impl Default for TrafficMix {
+    fn default() -> Self {
+        TrafficMix::new([
+            (VehicleKind::Car, 80.0),
+            (VehicleKind::Motorbike, 10.0),
+            (VehicleKind::Bus, 5.0),
+            (VehicleKind::Truck, 5.0),
+        ])
+    }
+}
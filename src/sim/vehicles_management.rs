@@ -1,136 +1,301 @@
-use std::{ collections::HashMap, time::{ Duration, Instant } };
-use sdl2::{ rect::Rect, render::{ Canvas, Texture }, video::Window };
-use rand::Rng;
-use crate::{ WINDOW_HEIGHT, WINDOW_WIDTH };
-use super::{ roads::{ Direction, Line, RoadDirection, RoadIntersection }, vehicle::Vehicle };
+use std::{ path::Path, time::Duration };
+use rand::{ rngs::StdRng, Rng, SeedableRng };
+use super::{
+    clock::Clock,
+    collision::CollisionDetector,
+    config::Config,
+    emergency::EmergencyPreemption,
+    geometry::Geometry,
+    pedestrians::Pedestrians,
+    policy::{ IntersectionPolicy, ReservationPolicy },
+    recording::{ Recorder, Session, SpawnRecord },
+    safety::SafetyMonitor,
+    roads::{ Direction, Line, RoadDirection, RoadIntersection },
+    scenario::{ Scenario, SpawnEvent },
+    trips::{ Trip, TripReport },
+    vehicle::Vehicle,
+    vehicle_type::{ TrafficMix, VehicleCatalog, VehicleKind, VehicleType },
+};
 
-pub const NORMAL_VELOCITY: i32 = 2;
-pub const SLOW_VELOCITY: i32 = 1;
-pub const STOP_VELOCITY: i32 = 0;
-pub const FAST_VELOCITY: i32 = 3;
-pub const SAFE_DISTANCE: i32 = 10;
+/// Meters per second (30 km/h).
+pub const NORMAL_VELOCITY: f64 = 30.0 / 3.6;
+/// Meters per second (15 km/h).
+pub const SLOW_VELOCITY: f64 = 15.0 / 3.6;
+pub const STOP_VELOCITY: f64 = 0.0;
+/// Meters per second (45 km/h).
+pub const FAST_VELOCITY: f64 = 45.0 / 3.6;
+/// Meters.
+pub const SAFE_DISTANCE: f64 = 1.0;
+/// Meters per second squared.
+pub const MAX_ACCELERATION: f64 = 2.5;
+pub const COMFORTABLE_DECELERATION: f64 = 3.5;
+pub const EMERGENCY_DECELERATION: f64 = 8.0;
+/// Seconds.
+pub const TIME_HEADWAY: f64 = 1.0;
 
-const SPAWN_COOLDOWN: Duration = Duration::from_millis(500);
+/// Default minimum simulated time between two spawns.
+pub const SPAWN_COOLDOWN: Duration = Duration::from_millis(500);
 
 #[derive(Debug)]
 pub struct VehiclesManagement {
     pub list: Vec<Vehicle>,
-    last_spawn_time: Option<Instant>,
+    pub clock: Clock,
+    last_spawn_tick: Option<u64>,
     pub intersection_list: Vec<i32>,
+    pub geometry: Geometry,
+    pub vehicle_types: VehicleCatalog,
+    /// Kinds of the vehicles spawned at random.
+    pub traffic_mix: TrafficMix,
+    pub spawn_cooldown: Duration,
     pub intersection: RoadIntersection,
+    /// Intersection and its crosswalks, the stop lines being at its edge.
+    pub stop_area: RoadIntersection,
+    pub policy: Box<dyn IntersectionPolicy>,
+    /// Right of way of emergency vehicles over the policy.
+    pub emergency: EmergencyPreemption,
+    /// Pedestrians crossing at the crosswalks, given the right of way over the policy too.
+    pub pedestrians: Pedestrians,
     pub number_of_vehicles: i32,
     pub number_passed_intersection: i32,
-    pub max_velocity: i32,
-    pub min_velocity: i32,
+    pub max_velocity: f64,
+    pub min_velocity: f64,
     pub max_time: Duration,
     pub min_time: Duration,
-    pub close_call: usize,
+    /// Actual overlaps of vehicle footprints.
+    pub collisions: CollisionDetector,
+    /// Surrogate safety measures (TTC, PET, DRAC) of every pair of vehicles.
+    pub safety: SafetyMonitor,
+    /// Trip of every spawned vehicle, indexed by vehicle id.
+    pub trips: Vec<Trip>,
+    /// Vehicles of the adjacent intersections of a `Network`, with the id -1: the vehicles
+    /// leaving this one keep their distance to them.
+    pub neighbours: Vec<Vehicle>,
+    /// Ids of the vehicles slowing down for a gap in the lane next to them to change lane
+    /// into, set by a `Network`.
+    pub yielding: Vec<i32>,
+    /// Vehicles that left the area of the intersection on the last tick.
+    pub exited: Vec<Vehicle>,
+    pub seed: u64,
+    rng: StdRng,
+    /// Scenario spawns not done yet, sorted by tick.
+    scheduled: Vec<SpawnEvent>,
+    recorder: Option<Recorder>,
 }
 
 impl VehiclesManagement {
     pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// Same as `new`, but random traffic is drawn from an RNG seeded with `seed`, so
+    /// the same seed and inputs always produce the same spawn sequence.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_config(seed, &Config::default())
+    }
+
+    /// Simulation of the crossroads and parameters described by `config`.
+    pub fn with_config(seed: u64, config: &Config) -> Self {
+        let geometry = config.geometry;
+        let intersection = geometry.intersection();
+        let policy = Box::new(ReservationPolicy::new(&geometry));
         VehiclesManagement {
             list: vec![],
-            last_spawn_time: None,
+            clock: Clock::new(config.tick_duration),
+            last_spawn_tick: None,
             intersection_list: Vec::new(),
-            intersection: RoadIntersection::new(362, 234, 300, 300),
+            geometry,
+            vehicle_types: config.vehicle_types.clone(),
+            traffic_mix: config.traffic_mix.clone(),
+            spawn_cooldown: config.spawn_cooldown,
+            intersection,
+            stop_area: geometry.stop_area(),
+            policy,
+            emergency: EmergencyPreemption::default(),
+            pedestrians: Pedestrians::new(&geometry, config.pedestrians),
             number_of_vehicles: 0,
             number_passed_intersection: 0,
-            max_velocity: 0,
-            min_velocity: 0,
+            max_velocity: 0.0,
+            min_velocity: 0.0,
             max_time: Duration::from_secs(0),
             min_time: Duration::from_secs(0),
-            close_call: 0,
+            collisions: CollisionDetector::default(),
+            safety: SafetyMonitor::new(&geometry, config.safety),
+            trips: Vec::new(),
+            neighbours: Vec::new(),
+            yielding: Vec::new(),
+            exited: Vec::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            scheduled: Vec::new(),
+            recorder: None,
         }
     }
 
-    pub fn spawn(&mut self, lines: &Vec<Line>) {
-        if let Some(last_spawn) = self.last_spawn_time {
-            if last_spawn.elapsed() < SPAWN_COOLDOWN {
-                return; // Cooldown not yet complete, don't spawn
-            }
+    /// Replace the right-of-way policy (the default is `ReservationPolicy`).
+    pub fn set_policy(&mut self, policy: Box<dyn IntersectionPolicy>) {
+        self.policy = policy;
+    }
+
+    pub fn spawn(&mut self, lines: &[Line]) {
+        let kind = self.random_kind();
+        self.spawn_kind(lines, kind);
+    }
+
+    /// Kind of a vehicle spawned at random, drawn from the traffic mix.
+    pub fn random_kind(&mut self) -> VehicleKind {
+        self.traffic_mix.pick(&mut self.rng)
+    }
+
+    /// Spawn a vehicle of `kind` on one of `lines`, with a random manoeuvre.
+    pub fn spawn_kind(&mut self, lines: &[Line], kind: VehicleKind) {
+        if lines.is_empty() || self.is_cooling_down() {
+            return;
         }
 
-        let random_road = self.random_roads(&lines);
-        // Create hashmap with all spawn
-        let position_map: HashMap<(RoadDirection, Direction), (i32, i32)> = [
-            // North
-            ((RoadDirection::North, Direction::Left), (462, 0)),
-            ((RoadDirection::North, Direction::Straight), (412, 0)),
-            ((RoadDirection::North, Direction::Right), (362, 0)),
-            // West
-            ((RoadDirection::West, Direction::Left), (WINDOW_WIDTH, 334)),
-            ((RoadDirection::West, Direction::Straight), (WINDOW_WIDTH, 284)),
-            ((RoadDirection::West, Direction::Right), (WINDOW_WIDTH, 234)),
-            // South
-            ((RoadDirection::South, Direction::Left), (512, WINDOW_HEIGHT)),
-            ((RoadDirection::South, Direction::Straight), (562, WINDOW_HEIGHT)),
-            ((RoadDirection::South, Direction::Right), (612, WINDOW_HEIGHT)),
-            // Est
-            ((RoadDirection::East, Direction::Left), (0, 384)),
-            ((RoadDirection::East, Direction::Straight), (0, 434)),
-            ((RoadDirection::East, Direction::Right), (0, 484)),
-        ]
-            .iter()
-            .cloned()
-            .collect();
-        // Get the vehicle spawn
-        if
-            let Some(&(x, y)) = position_map.get(
-                &(random_road.road_direction, random_road.direction)
-            )
-        {
-            if !self.check_spawn(x, y) {
-                self.list.push(
-                    Vehicle::new(
-                        self.number_of_vehicles,
-                        random_road.road_direction,
-                        random_road.direction,
-                        x,
-                        y
-                    )
-                );
-                self.number_of_vehicles += 1;
-                self.last_spawn_time = Some(Instant::now());
-                return;
-            }
+        let random_road = self.random_roads(lines);
+        if random_road.directions.is_empty() {
+            return;
         }
+        let direction = self.random_direction(&random_road.directions);
+        self.spawn_on(&random_road, direction, kind);
+    }
+
+    /// Spawn a vehicle of `kind` on `line` taking `direction` and return its id, unless the
+    /// spawn cooldown isn't complete or the spawn point is taken.
+    pub fn spawn_on(
+        &mut self,
+        line: &Line,
+        direction: Direction,
+        kind: VehicleKind
+    ) -> Option<i32> {
+        if self.is_cooling_down() {
+            return None;
+        }
+        let vehicle_type = self.vehicle_types.get(kind).clone();
+        if !self.push_vehicle(line, direction, &vehicle_type) {
+            return None;
+        }
+        self.last_spawn_tick = Some(self.clock.tick);
+        Some(self.number_of_vehicles - 1)
+    }
+
+    /// Whether the spawn cooldown since the last spawn isn't complete yet.
+    fn is_cooling_down(&self) -> bool {
+        self.last_spawn_tick.is_some_and(|last_spawn| {
+            self.clock.elapsed(last_spawn) < self.spawn_cooldown
+        })
+    }
+
+    /// Play back `scenario`, its ticks counting from now. Scenario spawns ignore the spawn
+    /// cooldown; a spawn whose spawn point is taken is retried on the following ticks.
+    pub fn play(&mut self, scenario: &Scenario) {
+        let now = self.clock.tick;
+        self.scheduled.extend(
+            scenario.events.iter().map(|event| SpawnEvent { tick: now + event.tick, ..event.clone() })
+        );
+        self.scheduled.sort_by_key(|event| event.tick);
+    }
+
+    /// Record the session to `path`: every spawn from now on and the state of `list` at the
+    /// end of every tick. `approaches` are the lines drawn when the recording is replayed.
+    pub fn record(&mut self, path: &Path, approaches: &[Vec<Line>]) -> Result<(), String> {
+        let session = Session {
+            seed: self.seed,
+            policy: self.policy.name().to_string(),
+            dt: self.clock.dt,
+            geometry: self.geometry,
+            approaches: approaches.to_vec(),
+        };
+        self.recorder = Some(Recorder::create(path, &session)?);
+        Ok(())
+    }
+
+    /// Stop recording, reporting any error met while writing the file.
+    pub fn finish_recording(&mut self) -> Result<(), String> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Crossing and travel time distributions of the trips so far.
+    pub fn trip_report(&self) -> TripReport {
+        TripReport::new(&self.trips, &self.clock)
+    }
+
+    /// Whether scenario spawns are still to come.
+    pub fn is_playing(&self) -> bool {
+        !self.scheduled.is_empty()
     }
 
     pub fn spawn_random(&mut self, lines: Vec<&Vec<Line>>) {
-        let mut rng = rand::thread_rng();
-        let rand_road_direction = rng.gen_range(0..lines.len());
+        if lines.is_empty() {
+            return;
+        }
+        let rand_road_direction = self.rng.gen_range(0..lines.len());
         self.spawn(lines[rand_road_direction]);
     }
 
-    fn random_roads(&self, lines: &Vec<Line>) -> Line {
-        let mut rng = rand::thread_rng();
-        let rand = rng.gen_range(0..lines.len());
-        lines[rand]
+    /// Spawn an emergency vehicle on a random approach.
+    pub fn spawn_emergency(&mut self, lines: Vec<&Vec<Line>>) {
+        if lines.is_empty() {
+            return;
+        }
+        let rand_road_direction = self.rng.gen_range(0..lines.len());
+        self.spawn_kind(lines[rand_road_direction], VehicleKind::Emergency);
+    }
+
+    /// Spawn a pedestrian at either end of the crosswalk of a random approach.
+    pub fn spawn_pedestrian(&mut self) {
+        let road = [
+            RoadDirection::North,
+            RoadDirection::West,
+            RoadDirection::South,
+            RoadDirection::East,
+        ][self.rng.gen_range(0..4)];
+        let from_start = self.rng.gen_bool(0.5);
+        self.pedestrians.spawn(road, from_start, &self.clock);
     }
 
+    fn random_roads(&mut self, lines: &[Line]) -> Line {
+        let rand = self.rng.gen_range(0..lines.len());
+        lines[rand].clone()
+    }
+
+    /// Manoeuvre of a vehicle spawned on a lane permitting `directions`.
+    fn random_direction(&mut self, directions: &[Direction]) -> Direction {
+        if directions.len() == 1 {
+            return directions[0];
+        }
+        directions[self.rng.gen_range(0..directions.len())]
+    }
+
+    /// Advance the simulation by one tick, unless it halted on a crash.
     pub fn update(&mut self) {
+        if self.collisions.is_halted() {
+            return;
+        }
+        self.exited.clear();
+        self.spawn_scheduled();
+
         // Update list of vehicle in intersection
         for vehicle in self.list.iter_mut() {
             if vehicle.is_in_intersection(&self.intersection) {
                 if !self.intersection_list.contains(&vehicle.id) {
-                    vehicle.intersection_entry_time = Some(Instant::now());
+                    vehicle.intersection_entry_tick = Some(self.clock.tick);
+                    self.trips[vehicle.id as usize].entry_tick = Some(self.clock.tick);
                     self.intersection_list.push(vehicle.id);
                 }
             } else {
-                if let Some(entry_time) = vehicle.intersection_entry_time.take() {
-                    let mut time_in_intersection = entry_time.elapsed();
-                    if time_in_intersection < Duration::from_millis(50) {
-                        time_in_intersection = Duration::from_millis(400);
-                    }
+                if let Some(entry_tick) = vehicle.intersection_entry_tick.take() {
+                    self.trips[vehicle.id as usize].exit_tick = Some(self.clock.tick);
+                    let time_in_intersection = self.clock.elapsed(entry_tick);
                     if self.max_time < time_in_intersection {
                         self.max_time = time_in_intersection;
                     }
-                    if self.min_time == Duration::from_secs(0) {
-                        self.min_time = time_in_intersection;
-                    } else if
-                        self.min_time > time_in_intersection &&
-                        time_in_intersection > Duration::from_millis(50)
+                    if
+                        self.number_passed_intersection == 0 ||
+                        self.min_time > time_in_intersection
                     {
                         self.min_time = time_in_intersection;
                     }
@@ -140,81 +305,176 @@ impl VehiclesManagement {
             }
         }
 
-        self.check_collision();
+        if self.pedestrians.is_due(&self.clock) {
+            self.spawn_pedestrian();
+        }
+        self.pedestrians.update(&self.list, &self.stop_area, &self.clock);
+
+        self.apply_policy();
+
+        self.safety.observe(&self.list, &self.trips, &self.clock);
 
         self.update_position();
 
+        self.collisions.detect(&self.list, &self.clock);
+        self.pedestrians.detect_conflicts(&self.list);
+
         // Remove vehicles if outside the screen
-        self.list.retain(|vehicle| {
-            vehicle.x >= -50 &&
-                vehicle.x <= WINDOW_WIDTH &&
-                vehicle.y >= -50 &&
-                vehicle.y <= WINDOW_HEIGHT
-        });
+        let geometry = self.geometry;
+        for vehicle in &self.list {
+            if !geometry.is_on_screen(vehicle) {
+                self.trips[vehicle.id as usize].despawn_tick = Some(self.clock.tick);
+                self.exited.push(vehicle.clone());
+            }
+        }
+        self.list.retain(|vehicle| geometry.is_on_screen(vehicle));
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.frame(
+                self.clock.tick,
+                &self.list,
+                &self.pedestrians.list,
+                self.policy.as_ref()
+            );
+        }
+
+        self.clock.advance();
     }
 
-    fn update_position(&mut self) {
-        for vehicle in &mut self.list {
-            // Update Position
-            match vehicle.road_direction {
-                RoadDirection::North => {
-                    vehicle.y += vehicle.velocity;
-                }
-                RoadDirection::South => {
-                    vehicle.y -= vehicle.velocity;
-                }
-                RoadDirection::West => {
-                    vehicle.x -= vehicle.velocity;
-                }
-                RoadDirection::East => {
-                    vehicle.x += vehicle.velocity;
-                }
+    /// Spawn the scenario vehicles that are due, keeping those that can't spawn yet.
+    fn spawn_scheduled(&mut self) {
+        let now = self.clock.tick;
+        let due = self.scheduled.partition_point(|event| event.tick <= now);
+        let mut waiting = vec![];
+        for event in self.scheduled.drain(..due).collect::<Vec<_>>() {
+            if !self.push_vehicle(&event.line, event.direction, &event.vehicle_type) {
+                waiting.push(event);
             }
+        }
+        self.scheduled.splice(0..0, waiting);
+    }
 
-            match (vehicle.road_direction, vehicle.direction) {
-                (RoadDirection::North, Direction::Left) if vehicle.y >= 384 => {
-                    vehicle.road_direction = RoadDirection::East;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::North, Direction::Right) if vehicle.y >= 234 => {
-                    vehicle.road_direction = RoadDirection::West;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::West, Direction::Right) if vehicle.x <= 612 => {
-                    vehicle.road_direction = RoadDirection::South;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::West, Direction::Left) if vehicle.x <= 462 => {
-                    vehicle.road_direction = RoadDirection::North;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::South, Direction::Right) if vehicle.y <= 484 => {
-                    vehicle.road_direction = RoadDirection::East;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::South, Direction::Left) if vehicle.y <= 334 => {
-                    vehicle.road_direction = RoadDirection::West;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::East, Direction::Right) if vehicle.x >= 362 => {
-                    vehicle.road_direction = RoadDirection::North;
-                    vehicle.direction = Direction::Straight;
-                }
-                (RoadDirection::East, Direction::Left) if vehicle.x >= 512 => {
-                    vehicle.road_direction = RoadDirection::South;
-                    vehicle.direction = Direction::Straight;
-                }
-                _ => {}
-            }
+    /// Add a vehicle at the start of `line` unless its spawn point is taken.
+    fn push_vehicle(&mut self, line: &Line, direction: Direction, vehicle_type: &VehicleType) -> bool {
+        let (x, y) = self.geometry.spawn_position(line, vehicle_type.length);
+        if self.check_spawn(x, y, vehicle_type) {
+            return false;
         }
+        let mut vehicle = Vehicle::new(
+            self.number_of_vehicles,
+            line.road_direction,
+            direction,
+            x,
+            y,
+            vehicle_type
+        );
+        vehicle.turn = self.geometry.turn_path(line, direction, vehicle.wheelbase());
+        self.admit(vehicle, line);
+        true
+    }
+
+    /// Take over `vehicle`, which left an adjacent intersection of a `Network` by the road
+    /// of `line`, where it goes on at its current velocity to take `direction`, and return
+    /// its id here.
+    pub fn enter(&mut self, vehicle: &Vehicle, line: &Line, direction: Direction) -> i32 {
+        let id = self.number_of_vehicles;
+        let vehicle = Vehicle {
+            id,
+            direction,
+            turn: self.geometry.turn_path(line, direction, vehicle.wheelbase()),
+            turn_progress: None,
+            intersection_entry_tick: None,
+            has_stop: false,
+            ..vehicle.clone()
+        };
+        self.admit(vehicle, line);
+        id
+    }
+
+    /// Have the vehicle `vehicle_id`, on its way to the intersection, take `direction` from
+    /// `line` instead of the manoeuvre it arrived with. Any right of way the policy granted
+    /// it for that manoeuvre is lost.
+    pub fn change_manoeuvre(&mut self, vehicle_id: i32, line: &Line, direction: Direction) {
+        let Some(vehicle) = self.list.iter_mut().find(|vehicle| vehicle.id == vehicle_id) else {
+            return;
+        };
+        vehicle.direction = direction;
+        vehicle.turn = self.geometry.turn_path(line, direction, vehicle.wheelbase());
+        let trip = &mut self.trips[vehicle_id as usize];
+        trip.direction = direction;
+        trip.free_flow_ticks = vehicle.free_flow_ticks(&self.intersection, self.clock.dt);
+        self.policy.hold_back(&[vehicle_id]);
     }
 
-    fn check_spawn(&self, x: i32, y: i32) -> bool {
+    /// Whether `vehicle` has room in its lane, to change lane into it: it overlaps none of
+    /// the other vehicles of `list` (those with another id), and should the vehicles ahead of
+    /// it brake, it would stop a safe distance behind them, as would the vehicles behind it.
+    /// A vehicle changing lane takes up the lane it moves to as well.
+    pub fn has_room(&self, vehicle: &Vehicle) -> bool {
+        let stopping = |behind: &Vehicle, ahead: &Vehicle| {
+            let braking = behind.profile.braking_distance(behind.velocity) -
+                ahead.profile.braking_distance(ahead.velocity);
+            behind.profile.safe_distance + braking.max(0.0)
+        };
+        self.list
+            .iter()
+            .filter(|other| other.id != vehicle.id)
+            .flat_map(|other| {
+                [Some(other.clone()), other.lane_change.map(|across| other.moved_across(across))]
+            })
+            .flatten()
+            .all(|other| {
+                let ahead = vehicle.gap_to(&other);
+                let behind = other.gap_to(vehicle);
+                ahead.is_none_or(|gap| gap >= stopping(vehicle, &other)) &&
+                    behind.is_none_or(|gap| gap >= stopping(&other, vehicle)) &&
+                    !vehicle.overlaps(&other)
+            })
+    }
+
+    /// Add `vehicle`, just arrived on `line`, and start its trip.
+    fn admit(&mut self, vehicle: Vehicle, line: &Line) {
+        let direction = vehicle.direction;
+        let mut trip = Trip::new(
+            self.number_of_vehicles,
+            line.road_direction,
+            direction,
+            vehicle.kind,
+            self.clock.tick
+        );
+        trip.free_flow_ticks = vehicle.free_flow_ticks(&self.intersection, self.clock.dt);
+        self.trips.push(trip);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.spawn(SpawnRecord {
+                tick: self.clock.tick,
+                id: self.number_of_vehicles,
+                line: line.clone(),
+                direction,
+                kind: vehicle.kind,
+            });
+        }
+        self.list.push(vehicle);
+        self.number_of_vehicles += 1;
+    }
+
+    fn update_position(&mut self) {
+        for vehicle in &mut self.list {
+            vehicle.step(self.clock.dt);
+        }
+    }
+
+    /// Whether a vehicle of `vehicle_type` can't spawn at (`x`, `y`): it enters at its
+    /// normal velocity, so it needs the room to stop behind the vehicles in its lane.
+    fn check_spawn(&self, x: f64, y: f64, vehicle_type: &VehicleType) -> bool {
+        let half_lane = self.geometry.to_meters(self.geometry.lane_width) / 2.0;
+        let profile = &vehicle_type.profile;
+        let stopping = profile.safe_distance * 2.0 + profile.braking_distance(profile.normal);
         for vehicle in &self.list {
+            let clearance = (vehicle_type.length + vehicle.length) / 2.0 + stopping;
             // Check vertical spawn
             if
-                (x - vehicle.x).abs() < 25 &&
-                (y - vehicle.y).abs() < 50 + SAFE_DISTANCE * 2 &&
+                (x - vehicle.x).abs() < half_lane &&
+                (y - vehicle.y).abs() < clearance &&
                 (vehicle.road_direction == RoadDirection::North ||
                     vehicle.road_direction == RoadDirection::South)
             {
@@ -222,8 +482,8 @@ impl VehiclesManagement {
             }
             // Check horizontal spawn
             if
-                (x - vehicle.x).abs() < 50 + SAFE_DISTANCE * 2 &&
-                (y - vehicle.y).abs() < 25 &&
+                (x - vehicle.x).abs() < clearance &&
+                (y - vehicle.y).abs() < half_lane &&
                 (vehicle.road_direction == RoadDirection::West ||
                     vehicle.road_direction == RoadDirection::East)
             {
@@ -233,88 +493,282 @@ impl VehiclesManagement {
         false
     }
 
-    fn check_collision(&mut self) {
-        let len = self.list.len();
-
-        for i in 0..len {
-            let mut should_stop = false;
-            let mut should_slow = false;
-            let mut is_ahead_clear = false;
-
-            for j in 0..len {
-                if i != j && self.list[i].has_to_slow(&self.list[j]) {
-                    should_slow = true;
-                    should_stop = false;
-                    is_ahead_clear = false;
-                    break;
-                }
+    /// Hold at the stop line the vehicles that can still stop there, braking hard if need be,
+    /// while the lane they leave the intersection by has no room for them to stop past the
+    /// stop area, e.g. behind the queue of the next intersection of a `Network`. The room is
+    /// what the vehicles in that lane would leave if they all braked now, less the length of
+    /// the vehicles ahead that are bound to get into it too, and has to fit the whole vehicle.
+    /// Returns the ids of the vehicles held.
+    fn keep_box_clear(&self, velocities: &mut [f64]) -> Vec<i32> {
+        let mut held = vec![];
+        let area = &self.stop_area;
+        let corners = [
+            (area.x, area.y),
+            (area.x + area.width, area.y),
+            (area.x, area.y + area.height),
+            (area.x + area.width, area.y + area.height),
+        ];
+        for (i, vehicle) in self.list.iter().enumerate() {
+            let Some(distance) = vehicle.distance_to_intersection(area) else {
+                continue;
+            };
+            if distance < vehicle.stopping_distance(vehicle.profile.emergency_deceleration) {
+                continue;
             }
-
-            for j in 0..len {
-                if i != j && self.list[i].will_collide(&self.list[j]) {
-                    should_stop = true;
-                    if !self.list[i].has_stop {
-                        self.close_call += 1;
-                    }
-                    self.list[i].has_stop = true;
-                    should_slow = false;
-                    is_ahead_clear = false;
-                    break;
+            let exit = vehicle.road_direction.after_turn(vehicle.direction);
+            let across = |x: f64, y: f64| match exit {
+                RoadDirection::North | RoadDirection::South => x,
+                RoadDirection::West | RoadDirection::East => y,
+            };
+            let lane = match vehicle.road_direction {
+                RoadDirection::North | RoadDirection::South => vehicle.x,
+                RoadDirection::West | RoadDirection::East => vehicle.y,
+            };
+            let lane = self.geometry.lane_at(vehicle.road_direction, lane);
+            let lane_center = self.geometry.to_meters(self.geometry.lane_center(exit, lane));
+            let half_lane = self.geometry.to_meters(self.geometry.lane_width) / 2.0;
+            // Coordinate along the exit road, and where it leaves the stop area
+            let (sin, cos) = exit.heading().to_radians().sin_cos();
+            let along = |x: f64, y: f64| x * cos + y * sin;
+            let edge = corners
+                .iter()
+                .map(|&(x, y)| along(x, y))
+                .fold(f64::NEG_INFINITY, f64::max);
+            let room =
+                vehicle.length +
+                vehicle.profile.safe_distance * 2.0 +
+                vehicle.profile.braking_distance(vehicle.profile.normal);
+            let mut queue: Vec<&Vehicle> = self.list
+                .iter()
+                .chain(self.neighbours.iter())
+                .filter(|other| {
+                    other.road_direction == exit &&
+                        other.turn_progress.is_none() &&
+                        other.distance_to_intersection(area).is_none() &&
+                        (across(other.x, other.y) - lane_center).abs() < half_lane
+                })
+                .collect();
+            queue.sort_by(|a, b| along(b.x, b.y).total_cmp(&along(a.x, a.y)));
+            // Where the rear of the last one would end up, each stopping comfortably or
+            // behind the one ahead, from the farthest one
+            let mut end = f64::INFINITY;
+            for other in queue {
+                let rear = along(other.x, other.y) - other.length / 2.0;
+                let stop = rear + other.stopping_distance(other.profile.comfortable_deceleration);
+                end = stop.min(end - other.profile.safe_distance - other.length);
+            }
+            // Then the vehicles on their way into the lane ahead of this one: turning into it
+            // in the stop area, or closer to the stop area or too close to stop before it
+            for other in &self.list {
+                if
+                    other.id == vehicle.id ||
+                    other.road_direction.after_turn(other.direction) != exit
+                {
+                    continue;
+                }
+                let is_ahead = match other.distance_to_intersection(area) {
+                    Some(other_distance) =>
+                        other_distance < distance ||
+                            other_distance <
+                                other.stopping_distance(other.profile.emergency_deceleration),
+                    None => other.road_direction != exit,
+                };
+                let (x, y) = other.turn.map_or((other.x, other.y), |turn| turn.end);
+                if is_ahead && (across(x, y) - lane_center).abs() < half_lane {
+                    end -= other.profile.safe_distance + other.length;
                 }
             }
-
-            if self.list[i].is_ahead_clear(&self.list) {
-                should_stop = false;
-                should_slow = false;
-                is_ahead_clear = true;
+            if end - edge < room {
+                velocities[i] = velocities[i].min(vehicle.velocity_to_stop_at(area, self.clock.dt));
+                held.push(vehicle.id);
             }
+        }
+        held
+    }
 
-            if self.list[i].is_front_clear(&self.list) {
-                should_stop = false;
+    /// Accelerate or brake every vehicle towards the velocity the policy wants it to move
+    /// with, unless an emergency vehicle needs it out of the way or a pedestrian crosses
+    /// in front of it.
+    fn apply_policy(&mut self) {
+        let mut velocities = self.policy.velocities(&self.list, &self.stop_area, &self.clock);
+        let mut changing = vec![];
+        // Vehicles changing lane take up the lane they move to as well
+        let moving: Vec<Vehicle> = self.list
+            .iter()
+            .chain(&self.neighbours)
+            .filter_map(|vehicle| Some(vehicle.moved_across(vehicle.lane_change?)))
+            .collect();
+        // Out of the intersection box, and behind the vehicles that already entered the next
+        // intersection or change lane, vehicles keep their distance to the vehicle ahead
+        // whatever the policy, in the lane they move to too
+        for (vehicle, velocity) in self.list.iter().zip(velocities.iter_mut()) {
+            let is_out = self.trips[vehicle.id as usize].exit_tick.is_some() &&
+                !self.intersection.overlaps(vehicle);
+            let moved = vehicle.lane_change.map(|across| vehicle.moved_across(across));
+            let gap = [
+                vehicle.gap_ahead(&self.neighbours),
+                vehicle.gap_ahead(&self.list).filter(|_| is_out),
+                vehicle.gap_ahead(&moving).filter(|_| !self.intersection.overlaps(vehicle)),
+            ]
+                .into_iter()
+                .chain(
+                    moved.iter().flat_map(|moved| {
+                        [&self.list, &self.neighbours, &moving].map(|others| moved.gap_ahead(others))
+                    })
+                )
+                .flatten()
+                .min_by(f64::total_cmp);
+            if let Some(gap) = gap {
+                let room = gap - vehicle.profile.safe_distance;
+                *velocity = velocity.min(vehicle.velocity_to_stop_within(room, self.clock.dt));
+            }
+            // A vehicle waiting for a gap eases off to let the lane next to it go by, down to
+            // half its normal velocity
+            if self.yielding.contains(&vehicle.id) {
+                let deceleration = vehicle.profile.comfortable_deceleration;
+                let slower = vehicle.velocity - deceleration * self.clock.dt.as_secs_f64();
+                *velocity = velocity.min(slower.max(vehicle.profile.normal / 2.0));
             }
+            // A vehicle changing lane is held at the stop line until it is through
+            if
+                vehicle.lane_change.is_some() &&
+                vehicle.distance_to_intersection(&self.stop_area).is_some()
+            {
+                let stop = vehicle.velocity_to_stop_at(&self.stop_area, self.clock.dt);
+                *velocity = velocity.min(stop);
+                changing.push(vehicle.id);
+            }
+        }
+        self.emergency.apply(&self.list, &mut velocities, &self.stop_area, self.clock.dt);
+        let mut held = self.pedestrians.yield_to_pedestrians(
+            &self.list,
+            &mut velocities,
+            &self.stop_area,
+            self.clock.dt
+        );
+        for vehicle_id in self.keep_box_clear(&mut velocities) {
+            if !held.contains(&vehicle_id) {
+                held.push(vehicle_id);
+            }
+        }
+        for &vehicle_id in self.emergency.held.iter().chain(&changing) {
+            if !held.contains(&vehicle_id) {
+                held.push(vehicle_id);
+            }
+        }
+        self.policy.hold_back(&held);
 
-            if should_stop {
-                self.list[i].velocity = STOP_VELOCITY;
-                self.list[i].has_stop = true;
-            } else if should_slow {
-                self.list[i].velocity = SLOW_VELOCITY;
-            } else if is_ahead_clear {
-                self.list[i].velocity = FAST_VELOCITY;
-            } else {
-                self.list[i].velocity = NORMAL_VELOCITY;
+        for (vehicle, velocity) in self.list.iter_mut().zip(velocities) {
+            let was_moving = vehicle.velocity != STOP_VELOCITY;
+            vehicle.accelerate(velocity, self.clock.dt);
+            if vehicle.velocity == STOP_VELOCITY {
+                let trip = &mut self.trips[vehicle.id as usize];
+                if was_moving {
+                    trip.stops += 1;
+                }
+                trip.stopped_ticks += 1;
+                if self.emergency.is_holding(vehicle.id) {
+                    trip.held_ticks += 1;
+                }
+                vehicle.has_stop = true;
             }
 
-            if self.max_velocity < self.list[i].velocity {
-                self.max_velocity = self.list[i].velocity;
+            if self.max_velocity < vehicle.velocity {
+                self.max_velocity = vehicle.velocity;
             }
-            if self.min_velocity >= self.list[i].velocity {
-                self.min_velocity = self.list[i].velocity;
+            if self.min_velocity >= vehicle.velocity {
+                self.min_velocity = vehicle.velocity;
             }
         }
     }
+}
 
-    pub fn render(&self, canvas: &mut Canvas<Window>, texture: &[&Texture]) {
-        for vehicle in &self.list {
-            if
-                (vehicle.x >= -50 || vehicle.x <= WINDOW_WIDTH) &&
-                (vehicle.y >= -50 || vehicle.y <= WINDOW_HEIGHT)
-            {
-                let dest_rect = Rect::new(vehicle.x, vehicle.y, 50, 50);
-                match vehicle.road_direction {
-                    RoadDirection::North => {
-                        canvas.copy(texture[0], None, dest_rect).unwrap();
-                    }
-                    RoadDirection::West => {
-                        canvas.copy(texture[1], None, dest_rect).unwrap();
-                    }
-                    RoadDirection::South => {
-                        canvas.copy(texture[2], None, dest_rect).unwrap();
-                    }
-                    RoadDirection::East => {
-                        canvas.copy(texture[3], None, dest_rect).unwrap();
-                    }
-                }
-            }
+impl Default for VehiclesManagement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::testing::spawn;
+
+    /// Vehicle of `kind` going straight on to the West, its front `distance` before the
+    /// stop area of `vehicles`.
+    fn approaching(
+        vehicles: &VehiclesManagement,
+        id: i32,
+        kind: VehicleKind,
+        distance: f64
+    ) -> Vehicle {
+        let config = Config::default();
+        let mut vehicle = spawn(id, &config, kind, RoadDirection::West, Direction::Straight);
+        let area = &vehicles.stop_area;
+        vehicle.x = area.x + area.width + distance + vehicle.length / 2.0;
+        vehicle
+    }
+
+    /// Ids of the vehicles `keep_box_clear` holds.
+    fn held(vehicles: &VehiclesManagement) -> Vec<i32> {
+        let mut velocities = vec![f64::INFINITY; vehicles.list.len()];
+        vehicles.keep_box_clear(&mut velocities)
+    }
+
+    #[test]
+    fn vehicles_are_held_unless_they_fit_in_their_exit_lane() {
+        let config = Config::default();
+        let mut vehicles = VehiclesManagement::with_config(0, &config);
+        // A car standing in the West exit lane 20 m past the stop area, the queue of the
+        // next intersection: room for a car, not for a bus
+        let mut last = approaching(&vehicles, -1, VehicleKind::Car, 0.0);
+        last.x = vehicles.stop_area.x - 20.0 - last.length / 2.0;
+        last.velocity = 0.0;
+        vehicles.neighbours.push(last);
+
+        vehicles.list = vec![approaching(&vehicles, 0, VehicleKind::Car, 30.0)];
+        assert!(held(&vehicles).is_empty());
+        vehicles.list = vec![approaching(&vehicles, 0, VehicleKind::Bus, 30.0)];
+        assert_eq!(held(&vehicles), [0]);
+        // The car ahead, too close to stop before the stop area, takes the room
+        vehicles.list = vec![
+            approaching(&vehicles, 0, VehicleKind::Car, 2.0),
+            approaching(&vehicles, 1, VehicleKind::Car, 30.0)
+        ];
+        assert_eq!(held(&vehicles), [1]);
+    }
+
+    #[test]
+    fn lane_change_waits_for_a_gap() {
+        let config = Config::default();
+        let lane = config.geometry.to_meters(config.geometry.lane_width);
+        let mut vehicles = VehiclesManagement::with_config(0, &config);
+        // A car in the right lane moving to the middle one
+        let mut vehicle = approaching(&vehicles, 0, VehicleKind::Car, 40.0);
+        vehicle.y -= lane;
+        let moved = vehicle.moved_across(vehicle.y + lane);
+        // A car at the same velocity in the middle lane, alongside, a car length behind, then
+        // far enough ahead
+        let mut other = approaching(&vehicles, 1, VehicleKind::Car, 40.0);
+        other.y = moved.y;
+        for (distance, has_room) in [(40.0, false), (45.0, false), (20.0, true)] {
+            other.x = approaching(&vehicles, 1, VehicleKind::Car, distance).x;
+            vehicles.list = vec![vehicle.clone(), other.clone()];
+            assert_eq!(
+                vehicles.has_room(&moved),
+                has_room,
+                "car {} m before the stop area",
+                distance
+            );
         }
+        // A car moving into the same lane from the left lane takes it up already
+        other.x = vehicle.x;
+        other.y = moved.y + lane;
+        other.lane_change = Some(moved.y);
+        vehicles.list = vec![vehicle.clone(), other.clone()];
+        assert!(!vehicles.has_room(&moved));
+        other.lane_change = None;
+        vehicles.list = vec![vehicle, other];
+        assert!(vehicles.has_room(&moved));
     }
 }
@@ -0,0 +1,42 @@
+use smart_road::sim::{
+    config::Config,
+    policy::{ self, POLICY_NAMES },
+    scenario::Scenario,
+    vehicles_management::VehiclesManagement,
+};
+
+/// Ticks a scenario may take to play out and empty the intersection.
+const MAX_TICKS: u64 = 20_000;
+
+/// Every scenario of the library runs to completion without a crash under each policy that
+/// coordinates the crossing. The reactive policy is left out: nothing keeps its vehicles
+/// apart in the intersection, which is what the other policies are compared against.
+#[test]
+fn scenarios_run_without_crashes() {
+    let config = Config::default();
+    let scenarios = Scenario::library();
+    assert!(!scenarios.is_empty(), "no scenario found in the library");
+    for name in &scenarios {
+        let scenario = Scenario::by_name(name, &config).unwrap();
+        for policy_name in POLICY_NAMES.into_iter().filter(|&name| name != "reactive") {
+            let mut vehicles = VehiclesManagement::with_config(0, &config);
+            vehicles.set_policy(
+                policy::by_name(policy_name, &vehicles.geometry, Default::default()).unwrap()
+            );
+            vehicles.play(&scenario);
+            let mut ticks = 0;
+            while vehicles.is_playing() || !vehicles.list.is_empty() {
+                assert!(ticks < MAX_TICKS, "{} under {} did not finish", name, policy_name);
+                vehicles.update();
+                ticks += 1;
+            }
+            assert!(
+                vehicles.collisions.crashes.is_empty(),
+                "{} under {}: {} crashes",
+                name,
+                policy_name,
+                vehicles.collisions.crashes.len()
+            );
+        }
+    }
+}